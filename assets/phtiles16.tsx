@@ -65,6 +65,10 @@
   <properties>
    <property name="background" type="bool" value="true"/>
   </properties>
+  <animation>
+   <frame tileid="15" duration="1500"/>
+   <frame tileid="9" duration="250"/>
+  </animation>
  </tile>
  <tile id="16">
   <properties>
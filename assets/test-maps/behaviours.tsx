@@ -0,0 +1,59 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.8" tiledversion="1.8.4" name="behaviours" tilewidth="16" tileheight="16" tilecount="36" columns="6">
+ <image source="../phtiles16.png" width="100" height="100"/>
+ <tile id="0">
+  <properties>
+   <property name="conveyor_right" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="1">
+  <properties>
+   <property name="hazard" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="2">
+  <properties>
+   <property name="bounce" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="3">
+  <properties>
+   <property name="slippery" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="4">
+  <properties>
+   <property name="slope" value="up"/>
+  </properties>
+ </tile>
+ <tile id="5">
+  <properties>
+   <property name="slope" value="down"/>
+  </properties>
+ </tile>
+ <tile id="6">
+  <properties>
+   <property name="slope" value="up_low"/>
+  </properties>
+ </tile>
+ <tile id="7">
+  <properties>
+   <property name="slope" value="up_high"/>
+  </properties>
+ </tile>
+ <tile id="8">
+  <properties>
+   <property name="slope" value="down_high"/>
+  </properties>
+ </tile>
+ <tile id="9">
+  <properties>
+   <property name="slope" value="down_low"/>
+  </properties>
+ </tile>
+ <tile id="10">
+  <properties>
+   <property name="ladder" type="bool" value="true"/>
+  </properties>
+ </tile>
+</tileset>
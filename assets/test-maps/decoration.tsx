@@ -0,0 +1,9 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.8" tiledversion="1.8.4" name="decoration" tilewidth="16" tileheight="16" tilecount="36" columns="6">
+ <image source="../phtiles16.png" width="100" height="100"/>
+ <tile id="1">
+  <properties>
+   <property name="background" type="bool" value="true"/>
+  </properties>
+ </tile>
+</tileset>
@@ -0,0 +1,62 @@
+use crate::enemy::EnemyHittable;
+use crate::physics::{raycast, IntRect};
+use crate::resources::SceneResources;
+use crate::vfx::{BeamEffect, ZapFlash};
+use hecs::CommandBuffer;
+use macroquad::math::Vec2;
+
+// a shot that hits the first enemy or wall along its line straight away, waiting in the world
+// until the next tick like a Blast so that weapons can fire it from a command buffer
+pub struct Beam {
+    pub(crate) from: Vec2,
+    pub(crate) dir: Vec2,
+    pub(crate) range: f32,
+    pub(crate) damage: u16,
+}
+
+impl Beam {
+    pub fn new(from: Vec2, dir: Vec2, range: f32, damage: u16) -> Self {
+        Self {
+            from,
+            dir,
+            range,
+            damage,
+        }
+    }
+
+    pub fn update(resources: &mut SceneResources, buffer: &mut CommandBuffer) {
+        let world = resources.world_ref.lock().unwrap();
+        for (id, beam) in world.query::<&Beam>().iter() {
+            buffer.despawn(id);
+            let wall = raycast(
+                &world,
+                &resources.body_index,
+                beam.from,
+                beam.dir,
+                beam.range,
+            );
+            let mut dist = wall.unwrap_or(beam.range);
+            let mut target = None;
+            for (en_id, (en, rect)) in world.query::<(&EnemyHittable, &IntRect)>().iter() {
+                if en.hp > 0 {
+                    // anything found is no further away than what was hit before
+                    if let Some(d) = rect.ray_distance(beam.from, beam.dir, dist) {
+                        dist = d;
+                        target = Some(en_id);
+                    }
+                }
+            }
+            if let Some(en_id) = target {
+                world
+                    .get::<&mut EnemyHittable>(en_id)
+                    .unwrap()
+                    .hurt(beam.damage);
+            }
+            let end = beam.from + beam.dir * dist;
+            buffer.spawn((BeamEffect::new(beam.from, end),));
+            if target.is_some() || wall.is_some() {
+                buffer.spawn((ZapFlash::new_from_centre(end.x as i32, end.y as i32),));
+            }
+        }
+    }
+}
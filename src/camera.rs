@@ -9,6 +9,15 @@ pub fn add_camera(world: &mut World, player_pos: Vec2) -> Vec2 {
     pos
 }
 
+// jump straight to the player instead of scrolling, for when they appear somewhere new
+pub fn reset_camera(world: &mut World, player_pos: Vec2) {
+    let pos = vec2(player_pos.x, player_pos.y - CAMERA_FLOOR_OFFSET);
+    for (_, (cam, v)) in world.query_mut::<(&mut PlayerCamera, &mut Vec2)>() {
+        cam.floor = pos.y;
+        *v = pos;
+    }
+}
+
 pub struct PlayerCamera {
     floor: f32,
 }
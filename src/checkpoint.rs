@@ -0,0 +1,229 @@
+use crate::camera::reset_camera;
+use crate::draw::{ColorRect, PlayerSprite};
+use crate::physics::{Actor, IntRect, PathMotion, Secrecy, TileBody, TriggerZone};
+use crate::player::Controller;
+use crate::resources::SceneResources;
+use crate::script::ScriptState;
+use crate::switch::Switch;
+use crate::weapon::new_weapon;
+use hecs::{CommandBuffer, Entity, World};
+use macroquad::color::{GRAY, GREEN};
+
+// what happens when the player dies, chosen by the "respawn" property of each map
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RespawnMode {
+    Restart,
+    Checkpoint,
+}
+
+impl RespawnMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "restart" => Some(Self::Restart),
+            "checkpoint" => Some(Self::Checkpoint),
+            _ => None,
+        }
+    }
+}
+
+pub struct Checkpoint {
+    pub(crate) active: bool,
+    // where the player's feet go when they respawn here
+    x: i32,
+    y: i32,
+}
+
+pub fn add_checkpoint(world: &mut World, x: i32, y: i32) {
+    let rect = IntRect::new(x - 4, y - 32, 8, 32);
+    world.spawn((
+        rect,
+        ColorRect::new(GRAY),
+        Checkpoint {
+            active: false,
+            x,
+            y,
+        },
+    ));
+}
+
+// everything needed to put the level back how it was when the player touched a checkpoint,
+// apart from enemies and pickups which stay dead or collected. since collected pickups don't
+// come back, the player keeps what they got from them too
+pub struct Snapshot {
+    player_rect: IntRect,
+    controller: Controller,
+    secrets: u32,
+    found_secrets: Vec<Entity>,
+    bodies: Vec<(Entity, i32, i32, Option<PathMotion>)>,
+    switches: Vec<(Entity, bool)>,
+    script: ScriptState,
+}
+
+impl Snapshot {
+    // for a wall jump pickup collected after the checkpoint was reached
+    pub fn unlock_wall_jump(&mut self) {
+        self.controller.wall_jump = true;
+    }
+}
+
+impl Checkpoint {
+    pub fn update(resources: &mut SceneResources, buffer: &mut CommandBuffer) {
+        if resources.respawn != RespawnMode::Checkpoint {
+            return;
+        }
+        let world = resources.world_ref.lock().unwrap();
+        let p_rect = match world.query_one::<(&IntRect, &Controller)>(resources.player_id) {
+            Ok(mut q) => match q.get() {
+                Some((rect, _)) => rect.clone(),
+                None => return,
+            },
+            Err(_) => return,
+        };
+        let mut touched = None;
+        for (id, (cp, rect)) in world.query::<(&Checkpoint, &IntRect)>().iter() {
+            if !cp.active && rect.intersects(&p_rect) {
+                touched = Some((id, cp.x, cp.y));
+            }
+        }
+        if let Some((touched, x, y)) = touched {
+            // only the last checkpoint touched is active
+            for (id, cp) in world.query::<&mut Checkpoint>().iter() {
+                cp.active = id == touched;
+                let color = if cp.active { GREEN } else { GRAY };
+                buffer.insert_one(id, ColorRect::new(color));
+            }
+            let start = IntRect::new(x - 8, y - p_rect.h, p_rect.w, p_rect.h);
+            let snapshot = take_snapshot(&world, resources, start);
+            resources.checkpoint = Some(snapshot);
+            resources.messages.add("Checkpoint reached.".to_owned());
+        }
+    }
+}
+
+fn take_snapshot(world: &World, resources: &SceneResources, player_rect: IntRect) -> Snapshot {
+    let mut q = world.query_one::<&Controller>(resources.player_id).unwrap();
+    let controller = q.get().unwrap();
+    Snapshot {
+        player_rect,
+        controller: controller.respawn(),
+        secrets: resources.stats.secrets,
+        found_secrets: world
+            .query::<&TriggerZone>()
+            .iter()
+            .filter(|(_, tz)| tz.secrecy == Secrecy::Found)
+            .map(|(id, _)| id)
+            .collect(),
+        bodies: world
+            .query::<(&TileBody, Option<&PathMotion>)>()
+            .iter()
+            .map(|(id, (body, pm))| (id, body.x, body.y, pm.cloned()))
+            .collect(),
+        switches: world
+            .query::<&Switch>()
+            .iter()
+            .map(|(id, s)| (id, s.enabled))
+            .collect(),
+        script: resources.script_engine.save_state(),
+    }
+}
+
+// a quicksave only says which checkpoint is active, so the snapshot to go back to is taken
+// again from the moment it's loaded
+pub fn restore_checkpoint(resources: &mut SceneResources) {
+    let mut world = resources.world_ref.lock().unwrap();
+    let checkpoints: Vec<(Entity, bool, i32, i32)> = world
+        .query::<&Checkpoint>()
+        .iter()
+        .map(|(id, cp)| (id, cp.active, cp.x, cp.y))
+        .collect();
+    for (id, active, x, y) in checkpoints {
+        let color = if active { GREEN } else { GRAY };
+        world.insert_one(id, ColorRect::new(color)).unwrap();
+        if active {
+            let (w, h) = {
+                let p_rect = world.get::<&IntRect>(resources.player_id).unwrap();
+                (p_rect.w, p_rect.h)
+            };
+            let start = IntRect::new(x - 8, y - h, w, h);
+            resources.checkpoint = Some(take_snapshot(&world, resources, start));
+        }
+    }
+}
+
+pub fn can_respawn(resources: &SceneResources) -> bool {
+    resources.respawn == RespawnMode::Checkpoint && resources.checkpoint.is_some()
+}
+
+// put the player back at the last checkpoint, returning false if the level should restart instead
+pub fn respawn(resources: &mut SceneResources) -> bool {
+    if !can_respawn(resources) {
+        return false;
+    }
+    let snapshot = resources.checkpoint.as_ref().unwrap();
+    let mut world = resources.world_ref.lock().unwrap();
+    // anything despawned since the snapshot was taken is left out
+    for (id, x, y, pm) in &snapshot.bodies {
+        let mut body = match world.get::<&mut TileBody>(*id) {
+            Ok(body) => body,
+            Err(_) => continue,
+        };
+        if body.indexed {
+            resources.body_index.remove_at(*id, &body.get_rect());
+        }
+        (body.x, body.y) = (*x, *y);
+        if body.indexed {
+            resources.body_index.insert_at(*id, &body.get_rect());
+        }
+        drop(body);
+        match pm {
+            Some(pm) => world.insert_one(*id, pm.clone()).unwrap(),
+            None => {
+                let _ = world.remove_one::<PathMotion>(*id);
+            }
+        }
+    }
+    for (id, enabled) in &snapshot.switches {
+        if let Ok(mut switch) = world.get::<&mut Switch>(*id) {
+            switch.enabled = *enabled;
+        }
+    }
+    for (id, tz) in world.query::<&mut TriggerZone>().iter() {
+        if tz.secrecy != Secrecy::NotSecret {
+            tz.secrecy = if snapshot.found_secrets.contains(&id) {
+                Secrecy::Found
+            } else {
+                Secrecy::Hidden
+            };
+        }
+    }
+    let rect = snapshot.player_rect.clone();
+    reset_camera(&mut world, rect.centre());
+    let actor = Actor::new(&rect, resources.movement.drag);
+    world
+        .insert(
+            resources.player_id,
+            (
+                rect,
+                actor,
+                snapshot.controller.respawn(),
+                PlayerSprite::new(),
+            ),
+        )
+        .unwrap();
+    drop(world);
+
+    // the weapons carried now are kept, but put back as they were when new
+    resources.weapons = resources
+        .persist_inventory()
+        .weapon_types
+        .iter()
+        .map(|t| new_weapon(*t))
+        .collect();
+    resources.selector.change(0.0);
+    resources.stats.secrets = snapshot.secrets;
+    resources
+        .script_engine
+        .restore_state(snapshot.script.clone());
+    resources.death_timer = None;
+    true
+}
@@ -0,0 +1,262 @@
+use crate::input::{Input, InputFrame};
+use crate::physics::IntRect;
+use crate::player::Controller;
+use crate::resources::{Inventory, SceneResources};
+use std::convert::TryFrom;
+
+// how often the player's state is written to a demo so that a desync gets noticed
+const CHECK_INTERVAL: u32 = 30;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Checksum {
+    x: i32,
+    y: i32,
+    hp: u8,
+}
+
+impl Checksum {
+    pub fn of(resources: &SceneResources) -> Self {
+        let world = resources.world_ref.lock().unwrap();
+        let (x, y) = world
+            .get::<&IntRect>(resources.player_id)
+            .map_or((0, 0), |r| (r.x, r.y));
+        let hp = world
+            .get::<&Controller>(resources.player_id)
+            .map_or(0, |c| c.hp);
+        Self { x, y, hp }
+    }
+}
+
+// a recording of the input for a single attempt at a level, stored as text:
+//   level <path>
+//   seed <n>
+//   weapons <index>... / ammo <n>... (only if the player didn't start with nothing)
+//   input <ticks> <down> <pressed> <any> (runs of identical ticks, keys as hex bitmasks and
+//     then 1 if any key was pressed, even one that isn't bound to anything)
+//   check <tick> <x> <y> <hp> (the player's state after that many ticks)
+#[derive(Clone)]
+pub struct Demo {
+    pub level: String,
+    pub seed: u64,
+    pub inventory: Inventory,
+    frames: Vec<InputFrame>,
+    checks: Vec<(u32, Checksum)>,
+}
+
+impl Demo {
+    pub fn new(level: &str, seed: u64, inventory: Inventory) -> Self {
+        Self {
+            level: level.to_owned(),
+            seed,
+            inventory,
+            frames: Vec::new(),
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("level {}", self.level),
+            format!("seed {}", self.seed),
+        ];
+        if !self.inventory.is_default {
+            lines.extend(self.inventory.to_lines());
+        }
+        let mut checks = self.checks.iter().peekable();
+        let mut tick = 0;
+        for run in self.frames.chunk_by(|a, b| a == b) {
+            // split runs at checks so that the lines stay in tick order
+            let mut remaining = run.len() as u32;
+            while remaining > 0 {
+                let n = match checks.peek() {
+                    Some((t, _)) if *t < tick + remaining => t - tick,
+                    _ => remaining,
+                };
+                if n > 0 {
+                    lines.push(format!(
+                        "input {} {:x} {:x} {}",
+                        n, run[0].down, run[0].pressed, run[0].any_pressed as u8
+                    ));
+                }
+                tick += n;
+                remaining -= n;
+                if remaining > 0 {
+                    let (t, c) = checks.next().unwrap();
+                    lines.push(format!("check {} {} {} {}", t, c.x, c.y, c.hp));
+                }
+            }
+        }
+        for (t, c) in checks {
+            lines.push(format!("check {} {} {} {}", t, c.x, c.y, c.hp));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut demo = Demo::new("", 0, Inventory::new());
+        for (idx, line) in text.lines().enumerate() {
+            let err = |what: &str| format!("line {} of demo: {}", idx + 1, what);
+            let mut parts = line.split_whitespace();
+            let keyword = parts.next();
+            let args: Vec<&str> = parts.collect();
+            let numbers = || -> Result<Vec<i64>, String> {
+                args.iter()
+                    .map(|a| a.parse().map_err(|_| err("bad number")))
+                    .collect()
+            };
+            match keyword {
+                None => (),
+                Some("level") => demo.level = args.join(" "),
+                Some("seed") => match args[..] {
+                    [seed] => demo.seed = seed.parse().map_err(|_| err("bad seed"))?,
+                    _ => return Err(err("seed needs one number")),
+                },
+                Some(kw @ ("weapons" | "ammo")) => {
+                    demo.inventory.parse_line(kw, &args).map_err(|e| err(&e))?;
+                }
+                Some("input") => {
+                    let n = args
+                        .first()
+                        .and_then(|a| a.parse::<usize>().ok())
+                        .ok_or_else(|| err("input needs a number of ticks"))?;
+                    let keys: Result<Vec<u32>, _> = args[1..]
+                        .iter()
+                        .map(|a| u32::from_str_radix(a, 16))
+                        .collect();
+                    let mut frame = match keys.as_deref() {
+                        Ok([down, pressed, any @ (0 | 1)]) => InputFrame {
+                            down: *down,
+                            pressed: *pressed,
+                            prev_down: 0,
+                            any_pressed: *any == 1,
+                        },
+                        _ => return Err(err("input needs two key masks and 0 or 1")),
+                    };
+                    // the keys down on the tick before aren't written out, since they
+                    // always match the previous input
+                    for _ in 0..n {
+                        frame.prev_down = demo.frames.last().map_or(0, |f| f.down);
+                        demo.frames.push(frame);
+                    }
+                }
+                Some("check") => match numbers()?[..] {
+                    [tick, x, y, hp] => {
+                        let range = |_| err("check value out of range");
+                        let c = Checksum {
+                            x: i32::try_from(x).map_err(range)?,
+                            y: i32::try_from(y).map_err(range)?,
+                            hp: u8::try_from(hp).map_err(range)?,
+                        };
+                        demo.checks.push((u32::try_from(tick).map_err(range)?, c));
+                    }
+                    _ => return Err(err("check needs four numbers")),
+                },
+                Some(other) => return Err(err(&format!("unknown line type {}", other))),
+            }
+        }
+        if demo.level.is_empty() {
+            return Err("demo doesn't say which level it's for".to_owned());
+        }
+        Ok(demo)
+    }
+}
+
+pub struct DemoRecorder {
+    demo: Demo,
+}
+
+impl DemoRecorder {
+    pub fn new(level: &str, seed: u64, inventory: Inventory) -> Self {
+        Self {
+            demo: Demo::new(level, seed, inventory),
+        }
+    }
+
+    // call with the input for each tick, just before it is simulated
+    pub fn record_input(&mut self, input: &Input) {
+        self.demo.frames.push(input.frame());
+    }
+
+    // and then with the result
+    pub fn record_state(&mut self, resources: &SceneResources) {
+        if self.check_due() {
+            self.record_check(Checksum::of(resources));
+        }
+    }
+
+    // the two halves of record_state, for when the recorder is part of the resources
+    pub fn check_due(&self) -> bool {
+        self.demo.ticks().is_multiple_of(CHECK_INTERVAL)
+    }
+
+    pub fn record_check(&mut self, check: Checksum) {
+        self.demo.checks.push((self.demo.ticks(), check));
+    }
+
+    // what the player started the level with
+    pub fn inventory(&self) -> &Inventory {
+        &self.demo.inventory
+    }
+
+    // the demo so far, without stopping recording
+    pub fn snapshot(&self) -> Demo {
+        self.demo.clone()
+    }
+
+    pub fn finish(self) -> Demo {
+        self.demo
+    }
+}
+
+pub struct DemoPlayer {
+    demo: Demo,
+    tick: u32,
+    next_check: usize,
+    desynced: bool,
+}
+
+impl DemoPlayer {
+    pub fn new(demo: Demo) -> Self {
+        Self {
+            demo,
+            tick: 0,
+            next_check: 0,
+            desynced: false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.demo.ticks()
+    }
+
+    pub fn play_input(&mut self, input: &mut Input) {
+        if let Some(frame) = self.demo.frames.get(self.tick as usize) {
+            input.set_frame(*frame);
+        }
+        self.tick += 1;
+    }
+
+    // returns an error the first time the simulation doesn't match the recording
+    pub fn check_state(&mut self, resources: &SceneResources) -> Result<(), String> {
+        match self.demo.checks.get(self.next_check) {
+            Some((t, expected)) if *t == self.tick => {
+                self.next_check += 1;
+                let actual = Checksum::of(resources);
+                if actual != *expected && !self.desynced {
+                    self.desynced = true;
+                    return Err(format!(
+                        "Demo desynced at tick {} (expected {:?}, got {:?})",
+                        self.tick, expected, actual
+                    ));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
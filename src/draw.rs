@@ -4,7 +4,7 @@ use crate::pickup::{Pickup, PickupType, WeaponPickup};
 use crate::resources::{GlobalAssets, SceneResources};
 use crate::switch::Switch;
 use crate::vfx::ZapFlash;
-use crate::weapon::{weapon_sprite_frame, AmmoType};
+use crate::weapon::{weapon_sprite_frame, Aim, AmmoType};
 use hecs::World;
 use macroquad::prelude::*;
 
@@ -13,7 +13,6 @@ pub(crate) struct ColorRect {
 }
 
 impl ColorRect {
-    #[allow(dead_code)] // will be used for a while each time we add a new actor
     pub fn new(color: Color) -> Self {
         Self { color }
     }
@@ -25,6 +24,8 @@ pub(crate) struct PlayerSprite {
     pub flipped: bool,
     pub blink: bool,
     pub muzzle_flash: u8,
+    pub climbing: bool,
+    pub aim: Aim,
 }
 
 impl PlayerSprite {
@@ -35,6 +36,23 @@ impl PlayerSprite {
             flipped: true,
             blink: false,
             muzzle_flash: 0,
+            climbing: false,
+            aim: Aim::forward(1),
+        }
+    }
+
+    // which frame of the player sheet to show
+    fn frame(&self) -> i32 {
+        // aiming up or down has its own pose whether the weapon is going off or not
+        match (self.aim.dx, self.aim.dy) {
+            (0, -1) => 5,
+            (_, -1) => 6,
+            (0, 1) => 8,
+            (_, 1) => 7,
+            _ if self.firing => 2,
+            // reaching up with each hand in turn as the ladder goes by
+            _ if self.climbing => 3 + self.n / 8 % 2,
+            _ => self.n * 5 % 2,
         }
     }
 }
@@ -87,11 +105,22 @@ impl SwitchSprite {
     }
 }
 
-pub(crate) struct ZapSprite {}
+pub(crate) struct ZapSprite {
+    pub(crate) angle: f32,
+    pub(crate) scale: f32,
+}
 
 impl ZapSprite {
     pub fn new() -> Self {
-        Self {}
+        Self::aimed(0.0)
+    }
+
+    pub fn aimed(angle: f32) -> Self {
+        Self { angle, scale: 1.0 }
+    }
+
+    pub fn charged(angle: f32) -> Self {
+        Self { angle, scale: 1.5 }
     }
 }
 
@@ -113,9 +142,13 @@ pub(crate) fn draw_tiles(world: &mut World, resources: &SceneResources) {
             for cx in cx_min..=cx_max {
                 let ii = ((cy * chunk.width) + cx) as usize;
                 if chunk.data[ii].is_visible() {
-                    let tsi = &resources.tileset_info;
-                    let sx = (chunk.tiles[ii] as u32 % tsi.columns) * tsi.tile_width;
-                    let sy = (chunk.tiles[ii] as u32 / tsi.columns) * tsi.tile_height;
+                    let tsi = resources.tileset_for(chunk.tiles[ii]);
+                    let mut id = chunk.tiles[ii] - tsi.first_gid;
+                    if let Some(anim) = tsi.animations.get(&id) {
+                        id = anim.tile_at(resources.stats.frames);
+                    }
+                    let sx = (id % tsi.columns) * tsi.tile_width;
+                    let sy = (id / tsi.columns) * tsi.tile_height;
                     draw_texture_ex(
                         tsi.texture,
                         tx as f32,
@@ -132,9 +165,9 @@ pub(crate) fn draw_tiles(world: &mut World, resources: &SceneResources) {
                         },
                     );
                 }
-                tx += chunk.size as i32;
+                tx += chunk.size;
             }
-            ty += chunk.size as i32;
+            ty += chunk.size;
         }
     }
 }
@@ -183,6 +216,7 @@ pub(crate) fn draw_sprites(world: &mut World, resources: &SceneResources, assets
                 PickupType::Ammo(AmmoType::Cell, _) => 16.0,
                 PickupType::Ammo(AmmoType::Shell, _) => 32.0,
                 PickupType::Ammo(AmmoType::Rocket, _) => 48.0,
+                PickupType::WallJump => 64.0,
             };
             draw_texture_ex(
                 assets.pickup_sprite,
@@ -213,16 +247,19 @@ pub(crate) fn draw_sprites(world: &mut World, resources: &SceneResources, assets
         }
     }
 
-    for (_, (rect, _spr)) in world.query::<(&IntRect, &ZapSprite)>().iter() {
+    for (_, (rect, spr)) in world.query::<(&IntRect, &ZapSprite)>().iter() {
         if rect.intersects(&camera_rect) {
+            // the sprite is drawn centred on the rect, which is only 8x5 for normal horizontal shots
+            let size = vec2(8.0, 5.0) * spr.scale;
             draw_texture_ex(
                 assets.zap_sprite,
-                rect.x as f32,
-                rect.y as f32,
+                rect.x as f32 + (rect.w as f32 - size.x) / 2.0,
+                rect.y as f32 + (rect.h as f32 - size.y) / 2.0,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(vec2(8.0, 5.0)),
+                    dest_size: Some(size),
                     source: Some(Rect::new(0.0, 2.0, 8.0, 5.0)),
+                    rotation: spr.angle,
                     ..Default::default()
                 },
             );
@@ -234,10 +271,16 @@ pub(crate) fn draw_sprites(world: &mut World, resources: &SceneResources, assets
             continue;
         }
         if spr.muzzle_flash < 6 {
+            let (fx, fy) = if spr.aim.dy == 0 {
+                (rect.x + if spr.flipped { 11 } else { -6 }, rect.y + 9)
+            } else {
+                let (mx, my) = spr.aim.muzzle(rect);
+                (mx - 4, my - 4)
+            };
             draw_texture_ex(
                 assets.zap_sprite,
-                rect.x as f32 + if spr.flipped { 11.0 } else { -6.0 },
-                rect.y as f32 + 9.0,
+                fx as f32,
+                fy as f32,
                 WHITE,
                 DrawTextureParams {
                     dest_size: Some(vec2(9.0, 9.0)),
@@ -246,7 +289,6 @@ pub(crate) fn draw_sprites(world: &mut World, resources: &SceneResources, assets
                 },
             );
         }
-        let frame = if spr.firing { 2 } else { spr.n * 5 % 2 };
         draw_texture_ex(
             assets.player_sprite,
             (rect.x - 1) as f32,
@@ -254,11 +296,22 @@ pub(crate) fn draw_sprites(world: &mut World, resources: &SceneResources, assets
             WHITE,
             DrawTextureParams {
                 dest_size: Some(vec2(16.0, 24.0)),
-                source: Some(Rect::new(0.0, 24.0 * frame as f32, 16.0, 24.0)),
+                source: Some(Rect::new(0.0, 24.0 * spr.frame() as f32, 16.0, 24.0)),
                 flip_x: spr.flipped,
                 ..Default::default()
             },
         );
+        let charge = resources.weapons.front().map_or(0.0, |w| w.charge());
+        if charge > 0.0 {
+            let color = if charge >= 1.0 { WHITE } else { YELLOW };
+            draw_rectangle(
+                rect.x as f32 - 2.0,
+                rect.y as f32 - 4.0,
+                (16.0 * charge).round(),
+                2.0,
+                color,
+            );
+        }
     }
 
     for (_, (rect, spr, hittable)) in world
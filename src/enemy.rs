@@ -1,10 +1,13 @@
 use std::cmp::Ordering;
 
 use crate::draw::{DogSprite, ParrotSprite};
-use crate::physics::{collide_any, Actor, IntRect};
+use crate::explosion::{explode, Blast};
+use crate::index::SpatialIndex;
+use crate::physics::{collide_any, ground_at, Actor, IntRect};
 use crate::player::Controller;
 use crate::projectile::{make_enemy_fireball, make_enemy_laser};
 use crate::resources::SceneResources;
+use crate::rng::Rng;
 use crate::vfx::create_explosion;
 use hecs::{CommandBuffer, Entity, World};
 use macroquad::prelude::*;
@@ -16,7 +19,10 @@ pub enum EnemyKind {
     SpiderParrot(ParrotKind),
 }
 
-pub fn add_enemy(world: &mut World, kind: EnemyKind, x: i32, y: i32) {
+// wrecks of enemies with this go off like a rocket instead of just burning
+pub struct Exploding;
+
+pub fn add_enemy(world: &mut World, kind: EnemyKind, exploding: bool, x: i32, y: i32) {
     let h = match kind {
         EnemyKind::SpiderParrot(_) => 24,
         _ => 16,
@@ -29,7 +35,7 @@ pub fn add_enemy(world: &mut World, kind: EnemyKind, x: i32, y: i32) {
     };
     let hittable = EnemyHittable::new(hp);
     let dmg = EnemyContactDamage::new();
-    if let EnemyKind::SpiderParrot(pk) = kind {
+    let id = if let EnemyKind::SpiderParrot(pk) = kind {
         world.spawn((
             kind,
             ParrotBehaviour::new(pk),
@@ -38,7 +44,7 @@ pub fn add_enemy(world: &mut World, kind: EnemyKind, x: i32, y: i32) {
             actor,
             hittable,
             dmg,
-        ));
+        ))
     } else {
         world.spawn((
             kind,
@@ -48,18 +54,13 @@ pub fn add_enemy(world: &mut World, kind: EnemyKind, x: i32, y: i32) {
             actor,
             hittable,
             dmg,
-        ));
+        ))
+    };
+    if exploding {
+        world.insert_one(id, Exploding).unwrap();
     }
 }
 
-fn with_prob(p: f32) -> bool {
-    quad_rand::gen_range(0.0, 1.0) < p
-}
-
-fn rand_sign() -> f32 {
-    quad_rand::gen_range(0, 2) as f32 * 2.0 - 1.0
-}
-
 fn player_x(world: &World, player_id: Entity) -> Option<f32> {
     world
         .get::<&IntRect>(player_id)
@@ -98,9 +99,9 @@ impl EnemyContactDamage {
     }
 }
 
-struct DogBehaviour {
-    dir: f32,
-    jump_y: Option<i32>,
+pub(crate) struct DogBehaviour {
+    pub(crate) dir: f32,
+    pub(crate) jump_y: Option<i32>,
 }
 
 impl DogBehaviour {
@@ -111,8 +112,8 @@ impl DogBehaviour {
         }
     }
 
-    pub fn update(world: &World, resources: &SceneResources) {
-        let player_x = player_x(world, resources.player_id);
+    pub fn update(world: &World, player_id: Entity, rng: &mut Rng) {
+        let player_x = player_x(world, player_id);
         for (_, (kind, actor, enemy, rect, spr)) in world
             .query::<(
                 &EnemyKind,
@@ -123,13 +124,13 @@ impl DogBehaviour {
             )>()
             .iter()
         {
-            if (actor.grounded || enemy.jump_y.is_some()) && with_prob(0.1) {
+            if (actor.grounded || enemy.jump_y.is_some()) && rng.with_prob(0.1) {
                 match player_x {
-                    Some(x) if with_prob(0.7) => {
+                    Some(x) if rng.with_prob(0.7) => {
                         enemy.dir = (x - rect.centre().x).signum() * 5.0;
                     }
                     _ => {
-                        enemy.dir = 5.0 * rand_sign();
+                        enemy.dir = 5.0 * rng.sign();
                     }
                 }
             }
@@ -139,7 +140,7 @@ impl DogBehaviour {
                     EnemyKind::JumpyDog => (0.2, -8.0),
                     _ => unreachable!(),
                 };
-                if with_prob(jump_prob) {
+                if rng.with_prob(jump_prob) {
                     actor.vy = jump_vel;
                     enemy.jump_y = Some(rect.y);
                 } else {
@@ -173,19 +174,19 @@ pub enum ParrotKind {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum ParrotState {
+pub(crate) enum ParrotState {
     Wait,
     Move,
     Attack,
     Fall,
 }
 
-struct ParrotBehaviour {
+pub(crate) struct ParrotBehaviour {
     kind: ParrotKind,
-    state: ParrotState,
-    state_timer: u8,
-    attack_timer: u8,
-    facing: i8,
+    pub(crate) state: ParrotState,
+    pub(crate) state_timer: u8,
+    pub(crate) attack_timer: u8,
+    pub(crate) facing: i8,
 }
 
 impl ParrotBehaviour {
@@ -204,8 +205,14 @@ impl ParrotBehaviour {
         self.state_timer = 0;
     }
 
-    pub fn update(world: &World, resources: &SceneResources, buffer: &mut CommandBuffer) {
-        let player_x = player_x(world, resources.player_id);
+    pub fn update(
+        world: &World,
+        player_id: Entity,
+        body_index: &SpatialIndex,
+        rng: &mut Rng,
+        buffer: &mut CommandBuffer,
+    ) {
+        let player_x = player_x(world, player_id);
         for (_, (actor, beh, rect, spr)) in world
             .query::<(
                 &mut Actor,
@@ -229,21 +236,20 @@ impl ParrotBehaviour {
                     }
                 }
                 ParrotState::Wait => {
-                    if quad_rand::gen_range(10, 20) < beh.state_timer {
-                        let is_facing_player = player_x.map_or(true, |x| {
-                            (x - rect.centre().x).signum() == beh.facing as f32
-                        });
-                        if with_prob(0.5) {
+                    if rng.gen_range(10, 20) < beh.state_timer {
+                        let is_facing_player = player_x
+                            .is_none_or(|x| (x - rect.centre().x).signum() == beh.facing as f32);
+                        if rng.with_prob(0.5) {
                             let will_attack = match beh.kind {
                                 ParrotKind::Laser => {
-                                    is_facing_player && beh.attack_timer == 0 && with_prob(0.85)
+                                    is_facing_player && beh.attack_timer == 0 && rng.with_prob(0.85)
                                 }
                                 ParrotKind::Cannon => {
                                     is_facing_player
                                         && beh.attack_timer == 0
-                                        && player_y(world, resources.player_id)
-                                            .map_or(false, |y| (y - rect.centre().y).abs() < 48.0)
-                                        && player_x.map_or(false, |x| {
+                                        && player_y(world, player_id)
+                                            .is_some_and(|y| (y - rect.centre().y).abs() < 48.0)
+                                        && player_x.is_some_and(|x| {
                                             let min_x = (x + 16.0).min(rect.centre().x);
                                             let max_x = (x - 16.0).max(rect.centre().x);
                                             let w = max_x - min_x;
@@ -253,7 +259,7 @@ impl ParrotBehaviour {
                                                 w as i32,
                                                 rect.h,
                                             );
-                                            !collide_any(world, &resources.body_index, &r)
+                                            !collide_any(world, body_index, &r)
                                         })
                                 }
                             };
@@ -263,15 +269,15 @@ impl ParrotBehaviour {
                                 beh.facing = -beh.facing;
                                 beh.state_timer = 0;
                             }
-                        } else if !parrot_should_stop(world, resources, rect, new_vx) {
+                        } else if !parrot_should_stop(world, body_index, rect, new_vx) {
                             beh.set_state(ParrotState::Move);
                         }
                     }
                 }
                 ParrotState::Move => {
                     spr.frame = (beh.state_timer / 2) % 2;
-                    if beh.state_timer > 10 && with_prob(0.05)
-                        || parrot_should_stop(world, resources, rect, new_vx)
+                    if beh.state_timer > 10 && rng.with_prob(0.05)
+                        || parrot_should_stop(world, body_index, rect, new_vx)
                     {
                         beh.set_state(ParrotState::Wait);
                     } else {
@@ -307,7 +313,7 @@ impl ParrotBehaviour {
                         }
                     }
                     if beh.state_timer % freq == freq - 1
-                        && parrot_off_edge(world, resources, rect, beh.facing)
+                        && parrot_off_edge(world, body_index, rect, beh.facing)
                     {
                         beh.set_state(ParrotState::Move);
                     } else if beh.state_timer >= limit {
@@ -321,46 +327,46 @@ impl ParrotBehaviour {
                 beh.attack_timer -= 1;
             }
             beh.state_timer += 1;
-            if beh.facing < 0 {
-                spr.flipped = false
-            } else {
-                spr.flipped = true
-            }
+            spr.flipped = beh.facing >= 0;
         }
     }
 }
 
-fn parrot_should_stop(world: &World, resources: &SceneResources, rect: &IntRect, vx: f32) -> bool {
+fn parrot_should_stop(world: &World, body_index: &SpatialIndex, rect: &IntRect, vx: f32) -> bool {
     let d = vx.abs().ceil() as i32;
     let (wall_rect_x, floor_rect_x) = match vx.total_cmp(&0.0) {
         Ordering::Equal => return false,
         Ordering::Less => (rect.x - d, rect.x - d),
         Ordering::Greater => (rect.x + rect.w, rect.x + rect.w + d - 1),
     };
-    let wall_rect = IntRect::new(wall_rect_x, rect.y, d, rect.h);
-    let floor_rect = IntRect::new(floor_rect_x, rect.y + rect.h, 1, 1);
-    collide_any(world, &resources.body_index, &wall_rect)
-        || !collide_any(world, &resources.body_index, &floor_rect)
+    // leaving out the bottom of the rect that move_actor would step up onto a slope
+    let wall_rect = IntRect::new(wall_rect_x, rect.y, d, (rect.h - d - 1).max(1));
+    // the floor ahead can be a little lower if it's a slope going down
+    collide_any(world, body_index, &wall_rect)
+        || !ground_at(world, body_index, floor_rect_x, rect.y + rect.h, d)
 }
 
 // detect whether the enemy's rear foot is sliding off a cliff as a result of firing recoil
-fn parrot_off_edge(world: &World, resources: &SceneResources, rect: &IntRect, facing: i8) -> bool {
+fn parrot_off_edge(world: &World, body_index: &SpatialIndex, rect: &IntRect, facing: i8) -> bool {
     let x = if facing > 0 {
         rect.x
     } else {
         rect.x + rect.w - 1
     };
-    !collide_any(
-        world,
-        &resources.body_index,
-        &IntRect::new(x, rect.y + rect.h, 1, 1),
-    )
+    // when standing on a slope the rear foot can be above the ground by up to the enemy's width
+    !ground_at(world, body_index, x, rect.y + rect.h, rect.w)
 }
 
 pub fn update_enemies(resources: &mut SceneResources, buffer: &mut CommandBuffer) {
     let world = resources.world_ref.lock().unwrap();
-    DogBehaviour::update(&world, resources);
-    ParrotBehaviour::update(&world, resources, buffer);
+    DogBehaviour::update(&world, resources.player_id, &mut resources.rng);
+    ParrotBehaviour::update(
+        &world,
+        resources.player_id,
+        &resources.body_index,
+        &mut resources.rng,
+        buffer,
+    );
 
     for (id, (actor, rect, kind, hittable)) in world
         .query::<(&Actor, &IntRect, &EnemyKind, &mut EnemyHittable)>()
@@ -381,7 +387,16 @@ pub fn update_enemies(resources: &mut SceneResources, buffer: &mut CommandBuffer
             }
             buffer.despawn(id);
             let (ex, ey) = rect.centre_int();
-            create_explosion(buffer, ex, ey);
+            if world.satisfies::<&Exploding>(id).unwrap_or(false) {
+                // which can set off other exploding enemies nearby
+                explode(
+                    buffer,
+                    &mut resources.cosmetic_rng,
+                    Blast::new(ex, ey, 24.0, 1, 4.0).hurting_player(),
+                );
+            } else {
+                create_explosion(buffer, &mut resources.cosmetic_rng, ex, ey);
+            }
             resources.stats.kills += 1
         }
     }
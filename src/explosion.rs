@@ -0,0 +1,104 @@
+use crate::enemy::EnemyHittable;
+use crate::physics::{line_obscured, Actor, IntRect};
+use crate::player::Controller;
+use crate::resources::SceneResources;
+use crate::rng::Rng;
+use crate::vfx::create_explosion;
+use hecs::CommandBuffer;
+use macroquad::math::{vec2, Vec2};
+
+// an explosion that hurts and pushes everything in range that it has a clear line to,
+// waiting in the world until the next tick so it can be spawned from a command buffer
+#[derive(Clone)]
+pub struct Blast {
+    pub(crate) centre: Vec2,
+    pub(crate) radius: f32,
+    pub(crate) damage: u16,
+    pub(crate) knockback: f32,
+    pub(crate) hurts_player: bool,
+}
+
+impl Blast {
+    pub fn new(x: i32, y: i32, radius: f32, damage: u16, knockback: f32) -> Self {
+        Self {
+            centre: vec2(x as f32, y as f32),
+            radius,
+            damage,
+            knockback,
+            hurts_player: false,
+        }
+    }
+
+    pub fn hurting_player(mut self) -> Self {
+        self.hurts_player = true;
+        self
+    }
+
+    // how much of the blast reaches a rect, from 1.0 at the centre down to 0.0 at the edge
+    fn strength_at(&self, rect: &IntRect) -> Option<(f32, Vec2)> {
+        let offset = rect.centre() - self.centre;
+        let d = offset.length();
+        if d >= self.radius {
+            return None;
+        }
+        let dir = if d > 0.0 { offset / d } else { vec2(0.0, -1.0) };
+        Some((1.0 - d / self.radius, dir))
+    }
+
+    pub fn update(resources: &mut SceneResources, buffer: &mut CommandBuffer) {
+        let world = resources.world_ref.lock().unwrap();
+        let blasts: Vec<Blast> = world
+            .query::<&Blast>()
+            .iter()
+            .map(|(id, b)| {
+                buffer.despawn(id);
+                b.clone()
+            })
+            .collect();
+        for blast in blasts {
+            for (_, (en, rect, actor)) in world
+                .query::<(&mut EnemyHittable, &IntRect, Option<&mut Actor>)>()
+                .iter()
+            {
+                if en.hp == 0 {
+                    continue;
+                }
+                if let Some((s, dir)) = blast.strength_at(rect) {
+                    if line_obscured(&world, blast.centre, rect.centre()) {
+                        continue;
+                    }
+                    let damage = (blast.damage as f32 * s).ceil() as u16;
+                    if damage > 0 {
+                        en.hurt(damage);
+                    }
+                    if let Some(actor) = actor {
+                        actor.vx += dir.x * blast.knockback * s;
+                        actor.vy += dir.y * blast.knockback * s;
+                    }
+                }
+            }
+            let q = world.query_one::<(&mut Controller, &mut Actor, &IntRect)>(resources.player_id);
+            if let Ok(mut q) = q {
+                if let Some((c, actor, rect)) = q.get() {
+                    if let Some((s, dir)) = blast.strength_at(rect) {
+                        if !line_obscured(&world, blast.centre, rect.centre()) {
+                            // the player can ride their own explosions even if they don't get hurt
+                            actor.vx += dir.x * blast.knockback * s;
+                            actor.vy += dir.y * blast.knockback * s;
+                            let damage = (blast.damage as f32 * s).ceil() as u16;
+                            if blast.hurts_player {
+                                c.hurt_by(damage.min(u8::MAX as u16) as u8);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the visible explosion together with its effect on the world
+pub fn explode(buffer: &mut CommandBuffer, rng: &mut Rng, blast: Blast) {
+    create_explosion(buffer, rng, blast.centre.x as i32, blast.centre.y as i32);
+    buffer.spawn((blast,));
+}
@@ -0,0 +1,34 @@
+use macroquad::file::load_file;
+use macroquad::texture::{load_texture, Texture2D};
+use std::path::PathBuf;
+
+// the game reads assets through macroquad, which needs a window (and fetches them
+// over http on wasm), but headless tools read them straight from a directory
+#[derive(Clone)]
+pub enum AssetSource {
+    Macroquad,
+    Directory(PathBuf),
+}
+
+impl AssetSource {
+    pub async fn load_file(&self, path: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Macroquad => load_file(path).await.map_err(|e| e.to_string()),
+            Self::Directory(dir) => std::fs::read(dir.join(path))
+                .map_err(|e| format!("Couldn't load file {}: {}", path, e)),
+        }
+    }
+
+    pub async fn load_string(&self, path: &str) -> Result<String, String> {
+        let data = self.load_file(path).await?;
+        Ok(String::from_utf8_lossy(&data).to_string())
+    }
+
+    pub async fn load_texture(&self, path: &str) -> Result<Texture2D, String> {
+        match self {
+            Self::Macroquad => load_texture(path).await.map_err(|e| e.to_string()),
+            // nothing is ever drawn without a window so don't bother decoding the image
+            Self::Directory(_) => Ok(Texture2D::empty()),
+        }
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 const BUCKET_SIZE: i32 = 128;
 
-pub(crate) struct SmallEntitySet {
+pub struct SmallEntitySet {
     pub elements: SmallVec<[Entity; 8]>
 }
 
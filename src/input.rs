@@ -1,3 +1,4 @@
+use enum_iterator::all;
 use macroquad::{
     input::{is_key_down, is_key_pressed, KeyCode},
     prelude::get_char_pressed,
@@ -8,10 +9,18 @@ use std::collections::HashSet;
 pub enum KeyState {
     Pressed,
     Held,
+    // the first tick after being let go
+    Released,
     NotPressed,
 }
 
-#[derive(PartialEq, Hash, Eq, Clone, Copy)]
+impl KeyState {
+    pub fn is_down(&self) -> bool {
+        matches!(self, Self::Pressed | Self::Held)
+    }
+}
+
+#[derive(enum_iterator::Sequence, PartialEq, Hash, Eq, Clone, Copy)]
 pub enum VirtualKey {
     Left,
     Right,
@@ -20,14 +29,19 @@ pub enum VirtualKey {
     Interact,
     PrevWeapon,
     NextWeapon,
+    Up,
+    Down,
+    QuickSave,
+    QuickLoad,
     DebugRestart,
     DebugWin,
     DebugKill,
     DebugAmmo,
     DebugProfile,
+    DebugGodMode,
 }
 
-const ALL_KEYS: [(KeyCode, VirtualKey); 12] = [
+const ALL_KEYS: [(KeyCode, VirtualKey); 16] = [
     (KeyCode::Left, VirtualKey::Left),
     (KeyCode::Right, VirtualKey::Right),
     (KeyCode::Z, VirtualKey::Jump),
@@ -35,6 +49,10 @@ const ALL_KEYS: [(KeyCode, VirtualKey); 12] = [
     (KeyCode::C, VirtualKey::Interact),
     (KeyCode::A, VirtualKey::PrevWeapon),
     (KeyCode::S, VirtualKey::NextWeapon),
+    (KeyCode::Up, VirtualKey::Up),
+    (KeyCode::Down, VirtualKey::Down),
+    (KeyCode::F5, VirtualKey::QuickSave),
+    (KeyCode::F9, VirtualKey::QuickLoad),
     (KeyCode::R, VirtualKey::DebugRestart),
     (KeyCode::W, VirtualKey::DebugWin),
     (KeyCode::K, VirtualKey::DebugKill),
@@ -42,9 +60,25 @@ const ALL_KEYS: [(KeyCode, VirtualKey); 12] = [
     (KeyCode::P, VirtualKey::DebugProfile),
 ];
 
+// the keys seen by one tick of the simulation, as bitmasks indexed by VirtualKey, along with
+// the keys that were down on the tick before and whether any key at all was pressed (which
+// includes keys that aren't bound to anything)
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputFrame {
+    pub down: u32,
+    pub pressed: u32,
+    pub prev_down: u32,
+    pub any_pressed: bool,
+}
+
+fn key_bit(vk: VirtualKey) -> u32 {
+    1 << vk as u32
+}
+
 pub struct Input {
     down: HashSet<VirtualKey>,
     pressed: HashSet<VirtualKey>,
+    prev_down: HashSet<VirtualKey>,
     any_pressed: bool,
 }
 
@@ -53,6 +87,7 @@ impl Input {
         Self {
             down: HashSet::new(),
             pressed: HashSet::new(),
+            prev_down: HashSet::new(),
             any_pressed: false,
         }
     }
@@ -67,9 +102,64 @@ impl Input {
                 self.pressed.insert(*vk);
             }
         }
+        #[cfg(debug_assertions)]
+        if is_key_down(KeyCode::Q) && is_key_down(KeyCode::D) {
+            self.down.insert(VirtualKey::DebugGodMode);
+        }
         self.any_pressed = get_char_pressed().is_some();
     }
 
+    // for driving the simulation without a keyboard
+    pub fn hold(&mut self, vk: VirtualKey) {
+        self.down.insert(vk);
+    }
+
+    pub fn press(&mut self, vk: VirtualKey) {
+        self.down.insert(vk);
+        self.pressed.insert(vk);
+        self.any_pressed = true;
+    }
+
+    // the keys that were down on the previous tick are kept, so a key can be released
+    pub fn clear(&mut self) {
+        self.down.clear();
+        self.pressed.clear();
+        self.any_pressed = false;
+    }
+
+    // call after each tick, so that the next one can tell which keys were let go
+    pub fn end_tick(&mut self) {
+        self.prev_down = self.down.clone();
+        self.pressed.clear();
+    }
+
+    pub fn frame(&self) -> InputFrame {
+        let mask = |keys: &HashSet<VirtualKey>| keys.iter().fold(0, |acc, vk| acc | key_bit(*vk));
+        InputFrame {
+            down: mask(&self.down),
+            pressed: mask(&self.pressed),
+            prev_down: mask(&self.prev_down),
+            any_pressed: self.any_pressed,
+        }
+    }
+
+    pub fn set_frame(&mut self, frame: InputFrame) {
+        self.clear();
+        self.prev_down.clear();
+        for vk in all::<VirtualKey>() {
+            if frame.down & key_bit(vk) != 0 {
+                self.down.insert(vk);
+            }
+            if frame.pressed & key_bit(vk) != 0 {
+                self.pressed.insert(vk);
+            }
+            if frame.prev_down & key_bit(vk) != 0 {
+                self.prev_down.insert(vk);
+            }
+        }
+        self.any_pressed = frame.any_pressed;
+    }
+
     pub fn is_down(&self, vk: VirtualKey) -> bool {
         self.down.contains(&vk)
     }
@@ -85,6 +175,9 @@ impl Input {
         if self.down.contains(&vk) {
             return KeyState::Held;
         }
+        if self.prev_down.contains(&vk) {
+            return KeyState::Released;
+        }
         KeyState::NotPressed
     }
 
@@ -93,7 +186,29 @@ impl Input {
     }
 
     pub fn reset(&mut self) {
-        self.pressed.clear();
+        self.end_tick();
         while get_char_pressed().is_some() {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_states() {
+        let mut input = Input::new();
+        input.press(VirtualKey::Fire);
+        assert!(input.state(VirtualKey::Fire) == KeyState::Pressed);
+        input.end_tick();
+        assert!(input.state(VirtualKey::Fire) == KeyState::Held);
+        input.clear();
+        assert!(input.state(VirtualKey::Fire) == KeyState::Released);
+        // a frame carries the keys from the tick before, so replaying it gives the same states
+        let frame = input.frame();
+        input.end_tick();
+        assert!(input.state(VirtualKey::Fire) == KeyState::NotPressed);
+        input.set_frame(frame);
+        assert!(input.state(VirtualKey::Fire) == KeyState::Released);
+    }
+}
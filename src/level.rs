@@ -2,25 +2,42 @@ use macroquad::prelude::load_string;
 use std::num::NonZeroUsize;
 
 use crate::resources::GlobalAssets;
+use crate::stats::parse_time;
 
 #[derive(Clone)]
 pub struct LevelInfo {
     pub number: Option<NonZeroUsize>,
     pub path: String,
     pub name: String,
+    // in frames
+    pub par: Option<u32>,
 }
 
 pub async fn load_level_info() -> Vec<LevelInfo> {
     let raw_level_info = load_string("levels.txt").await.unwrap();
-    raw_level_info
-        .lines()
+    parse_level_info(&raw_level_info)
+}
+
+// each line is a path, then optionally a par time like 1:30, then the name
+pub fn parse_level_info(text: &str) -> Vec<LevelInfo> {
+    text.lines()
         .enumerate()
         .map(|(idx, line)| {
             let mut parts = line.splitn(2, ' ');
+            let path = parts.next().unwrap().to_string();
+            let rest = parts.next().unwrap();
+            let (par, name) = match rest.split_once(' ') {
+                Some((first, name)) => match parse_time(first) {
+                    Some(par) => (Some(par), name),
+                    None => (None, rest),
+                },
+                None => (None, rest),
+            };
             LevelInfo {
                 number: NonZeroUsize::new(idx + 1),
-                path: parts.next().unwrap().to_string(),
-                name: parts.next().unwrap().to_string(),
+                path,
+                name: name.to_string(),
+                par,
             }
         })
         .collect()
@@ -41,6 +58,13 @@ impl GlobalAssets {
         }
     }
 
+    // levels outside the campaign don't have one, and neither does the first level
+    pub fn get_previous_level(&self, info: &LevelInfo) -> Option<LevelInfo> {
+        info.number
+            .and_then(|n| n.get().checked_sub(2))
+            .map(|idx| self.levels[idx].clone())
+    }
+
     pub fn get_level_with_path(&self, path: &str) -> LevelInfo {
         match self.levels.iter().position(|info| info.path == path) {
             Some(p) => self.levels[p].clone(),
@@ -48,7 +72,22 @@ impl GlobalAssets {
                 number: None,
                 path: path.to_owned(),
                 name: "???".to_owned(),
+                par: None,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_list_par_times() {
+        let levels = parse_level_info("intro 0:45 Entryway\nchasm The Chasm\n");
+        assert_eq!(levels[0].name, "Entryway");
+        assert_eq!(levels[0].par, Some(45 * 30));
+        assert_eq!(levels[1].name, "The Chasm");
+        assert_eq!(levels[1].par, None);
+    }
+}
@@ -0,0 +1,43 @@
+// lots of components have a no-argument constructor but a Default impl would be misleading
+#![allow(clippy::new_without_default)]
+
+mod beam;
+mod camera;
+pub mod checkpoint;
+pub mod demo;
+mod draw;
+mod enemy;
+pub mod explosion;
+mod files;
+mod index;
+pub mod input;
+pub mod level;
+pub mod lint;
+pub mod liquid;
+pub mod loader;
+mod messages;
+mod movement;
+mod objects;
+pub mod physics;
+mod pickup;
+pub mod player;
+pub mod profile;
+pub mod projectile;
+pub mod quicksave;
+pub mod render;
+pub mod resources;
+pub mod rng;
+pub mod save;
+pub mod scene;
+mod script;
+pub mod sim;
+mod stats;
+pub mod switch;
+pub mod timer;
+pub mod transition;
+pub mod vfx;
+mod visibility;
+pub mod weapon;
+
+pub const RENDER_W: u32 = 320;
+pub const RENDER_H: u32 = 200;
@@ -0,0 +1,245 @@
+use crate::files::AssetSource;
+use crate::liquid::LiquidKind;
+use crate::loader::{flatten_layers, tile_bounds, LoadError, LoadingManager};
+use crate::objects::ObjectRegistry;
+use crate::script::ScriptEngine;
+use crate::sim::now_or_never;
+use rhai::{Engine, ParseErrorType, Scope, Token};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// entry points that the game calls even if no trigger or switch mentions them
+const ENTRY_POINTS: [&str; 1] = ["init"];
+
+#[derive(Default)]
+struct MapNames {
+    entities: Vec<String>,
+    paths: Vec<String>,
+    zones: Vec<String>,
+    secrets: Vec<String>,
+    switches: Vec<String>,
+}
+
+// load a level's map and script without running anything and describe any problems found
+pub fn check_level(assets_dir: &Path, path: &str) -> Result<Vec<String>, LoadError> {
+    let source = AssetSource::Directory(assets_dir.to_owned());
+    let map_name = format!("{}.tmx", path);
+    let script_name = format!("{}.rhai", path);
+    let mut manager = LoadingManager::new(source.clone());
+    let map = now_or_never(manager.load_map(&map_name))?;
+    let registry = now_or_never(ObjectRegistry::load(&source))?;
+    let script = now_or_never(source.load_string(&script_name))
+        .map_err(|_| LoadError::MissingFile(script_name.clone()))?;
+
+    let mut problems = Vec::new();
+    let mut names = MapNames::default();
+    collect_names(&map, &map_name, &registry, &mut names, &mut problems);
+
+    let undefined = undefined_identifiers(&script, &script_name, &names)?;
+    for (name, line) in undefined {
+        problems.push(format!(
+            "{} line {}: {} is not a layer, path or switch in {}",
+            script_name, line, name, map_name
+        ));
+    }
+
+    let bodies = function_mentions(&script);
+    for zone in &names.zones {
+        let enter = format!("{}_enter", zone);
+        let exit = format!("{}_exit", zone);
+        if !bodies.contains_key(&enter) && !bodies.contains_key(&exit) {
+            problems.push(format!(
+                "{}: trigger zone {} has no {} or {} function",
+                script_name, zone, enter, exit
+            ));
+        }
+    }
+
+    let reached = reachable_functions(&bodies, &names);
+    let mut unreached: Vec<&String> = bodies.keys().filter(|f| !reached.contains(*f)).collect();
+    unreached.sort();
+    for f in unreached {
+        problems.push(format!(
+            "{}: function {} can't be reached from init, a trigger or a switch",
+            script_name, f
+        ));
+    }
+    Ok(problems)
+}
+
+fn collect_names(
+    map: &tiled::Map,
+    map_name: &str,
+    registry: &ObjectRegistry,
+    names: &mut MapNames,
+    problems: &mut Vec<String>,
+) {
+    let mut layers = Vec::new();
+    flatten_layers(map.layers(), &tiled::Properties::new(), &mut layers);
+    for (layer, _) in layers {
+        match layer.layer_type() {
+            tiled::LayerType::Tiles(data) => {
+                if tile_bounds(&data).is_some() {
+                    names.entities.push(layer.name.clone());
+                } else {
+                    problems.push(format!(
+                        "{}: layer {} has no tiles so it won't be loaded",
+                        map_name, layer.name
+                    ));
+                }
+            }
+            tiled::LayerType::Objects(data) => {
+                for obj in data.objects() {
+                    match &obj.shape {
+                        tiled::ObjectShape::Polyline { .. }
+                        | tiled::ObjectShape::Polygon { .. } => names.paths.push(obj.name.clone()),
+                        tiled::ObjectShape::Rect { .. } => {
+                            if obj.user_type == "secret" {
+                                names.secrets.push(obj.name.clone());
+                            } else if obj.user_type != "ladder"
+                                && LiquidKind::from_name(&obj.user_type).is_none()
+                            {
+                                names.zones.push(obj.name.clone());
+                            }
+                        }
+                        tiled::ObjectShape::Point(x, y) => {
+                            if obj.user_type == "switch" {
+                                names.switches.push(obj.name.clone());
+                            } else if !registry.is_known(&obj.user_type) {
+                                problems.push(format!(
+                                    "{}: unknown object type {} at ({}, {})",
+                                    map_name, obj.user_type, x, y
+                                ));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// compile the script in strict mode, adding each unknown name to the scope and trying
+// again so that every one of them gets reported along with the line it was first used on
+fn undefined_identifiers(
+    script: &str,
+    script_name: &str,
+    names: &MapNames,
+) -> Result<Vec<(String, usize)>, LoadError> {
+    let mut engine = Engine::new_raw();
+    engine.set_strict_variables(true);
+    let mut scope = Scope::new();
+    for name in ScriptEngine::builtin_names() {
+        scope.push(name, ());
+    }
+    for name in names
+        .entities
+        .iter()
+        .chain(names.paths.iter())
+        .chain(names.switches.iter())
+    {
+        scope.push(name.clone(), ());
+    }
+    let mut undefined = Vec::new();
+    loop {
+        match engine.compile_with_scope(&scope, script) {
+            Ok(_) => return Ok(undefined),
+            Err(err) => match &*err.0 {
+                ParseErrorType::VariableUndefined(name) if !scope.contains(name) => {
+                    undefined.push((name.clone(), err.1.line().unwrap_or(0)));
+                    scope.push(name.clone(), ());
+                }
+                _ => {
+                    return Err(LoadError::ScriptCompile {
+                        path: script_name.to_owned(),
+                        message: err.0.to_string(),
+                        line: err.1.line().unwrap_or(0),
+                        column: err.1.position().unwrap_or(0),
+                    })
+                }
+            },
+        }
+    }
+}
+
+// the names and strings in the body of each function, read with rhai's tokenizer so that
+// braces in strings and comments don't end a function early
+fn function_mentions(script: &str) -> HashMap<String, Vec<String>> {
+    let engine = Engine::new_raw();
+    let inputs = [script];
+    let (tokens, _) = engine.lex(&inputs);
+    let mut mentions: HashMap<String, Vec<String>> = HashMap::new();
+    let mut depth = 0;
+    let mut expect_name = false;
+    // the function being read and how deep in braces it was defined
+    let mut current: Option<(String, i32)> = None;
+    for (token, _) in tokens {
+        match token {
+            Token::Fn if current.is_none() => expect_name = true,
+            Token::Identifier(name) if expect_name => {
+                mentions.entry(name.to_string()).or_default();
+                current = Some((name.to_string(), depth));
+                expect_name = false;
+            }
+            Token::LeftBrace | Token::MapStart => depth += 1,
+            Token::RightBrace => {
+                depth -= 1;
+                if matches!(&current, Some((_, d)) if *d == depth) {
+                    current = None;
+                }
+            }
+            Token::Identifier(word) | Token::StringConstant(word) => match &current {
+                Some((f, d)) if depth > *d => {
+                    mentions.get_mut(f).unwrap().push(word.to_string());
+                }
+                _ => (),
+            },
+            Token::EOF => break,
+            _ => (),
+        }
+    }
+    mentions
+}
+
+fn reachable_functions(bodies: &HashMap<String, Vec<String>>, names: &MapNames) -> HashSet<String> {
+    let mut todo: Vec<String> = ENTRY_POINTS.iter().map(|s| s.to_string()).collect();
+    for zone in names.zones.iter().chain(names.secrets.iter()) {
+        todo.push(format!("{}_enter", zone));
+        todo.push(format!("{}_exit", zone));
+    }
+    for switch in &names.switches {
+        todo.push(format!("{}_interact", switch));
+    }
+    let mut reached = HashSet::new();
+    while let Some(f) = todo.pop() {
+        if let Some(body) = bodies.get(&f) {
+            if reached.insert(f) {
+                // calls, function pointers and closures all mention the function by name
+                for word in body {
+                    if bodies.contains_key(word) && !reached.contains(word) {
+                        todo.push(word.clone());
+                    }
+                }
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn braces_in_strings_and_comments() {
+        let script = "fn init() { print(\"}\"); // {\n a(); }\nfn a() { /* } */ b(); }\nfn b() {}";
+        let bodies = function_mentions(script);
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies["init"].contains(&"a".to_owned()));
+        assert!(bodies["a"].contains(&"b".to_owned()));
+        let reached = reachable_functions(&bodies, &MapNames::default());
+        assert_eq!(reached.len(), 3);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::draw::ColorRect;
+use crate::enemy::EnemyHittable;
+use crate::physics::{Actor, IntRect, TileBody};
+use crate::resources::SceneResources;
+use hecs::{Entity, World};
+use macroquad::color::Color;
+
+// how often acid and lava hurt enemies that are in them
+const DAMAGE_INTERVAL: u32 = 15;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LiquidKind {
+    Water,
+    Acid,
+    Lava,
+}
+
+impl LiquidKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "water" => Some(Self::Water),
+            "acid" => Some(Self::Acid),
+            "lava" => Some(Self::Lava),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Water => "water",
+            Self::Acid => "acid",
+            Self::Lava => "lava",
+        }
+    }
+
+    // damage to enemies every DAMAGE_INTERVAL ticks, the player just gets hurt whenever they can be
+    pub fn damage(&self) -> u16 {
+        match self {
+            Self::Water => 0,
+            Self::Acid => 1,
+            Self::Lava => 3,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Water => Color::new(0.2, 0.4, 1.0, 0.4),
+            Self::Acid => Color::new(0.4, 1.0, 0.1, 0.5),
+            Self::Lava => Color::new(1.0, 0.4, 0.0, 0.7),
+        }
+    }
+}
+
+pub struct Liquid {
+    pub kind: LiquidKind,
+    // a body the volume moves along with and its position relative to it
+    attached: Option<(Entity, i32, i32)>,
+}
+
+pub fn add_liquid(world: &mut World, kind: LiquidKind, rect: IntRect) -> Entity {
+    let draw = ColorRect::new(kind.color());
+    world.spawn((
+        rect,
+        draw,
+        Liquid {
+            kind,
+            attached: None,
+        },
+    ))
+}
+
+impl Liquid {
+    // make the volume move along with a body, keeping its current position relative to it
+    pub fn attach(&mut self, rect: &IntRect, body_id: Entity, body: &TileBody) {
+        self.attached = Some((body_id, rect.x - body.x, rect.y - body.y));
+    }
+
+    pub fn update(resources: &SceneResources) {
+        let world = resources.world_ref.lock().unwrap();
+        for (_, (liquid, rect)) in world.query::<(&Liquid, &mut IntRect)>().iter() {
+            // the loader won't attach a liquid to anything but a tile layer, but if the body
+            // has gone since then the volume just stays where it is
+            if let Some((id, dx, dy)) = liquid.attached {
+                if let Ok(body) = world.get::<&TileBody>(id) {
+                    rect.x = body.x + dx;
+                    rect.y = body.y + dy;
+                }
+            }
+        }
+        if resources.stats.frames.is_multiple_of(DAMAGE_INTERVAL) {
+            for (_, (actor, hittable)) in world.query::<(&Actor, &mut EnemyHittable)>().iter() {
+                if let Some(kind) = actor.liquid {
+                    if kind.damage() > 0 {
+                        hittable.hurt(kind.damage());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// copy out the liquid volumes so they can be checked while the actors are borrowed
+pub fn liquid_volumes(world: &World) -> Vec<(LiquidKind, IntRect)> {
+    world
+        .query::<(&Liquid, &IntRect)>()
+        .iter()
+        .map(|(_, (liquid, rect))| (liquid.kind, rect.clone()))
+        .collect()
+}
+
+// the liquid, if any, at a point
+pub fn liquid_at(volumes: &[(LiquidKind, IntRect)], x: i32, y: i32) -> Option<LiquidKind> {
+    let point = IntRect::new(x, y, 1, 1);
+    volumes
+        .iter()
+        .find(|(_, rect)| rect.intersects(&point))
+        .map(|(kind, _)| *kind)
+}
@@ -1,35 +1,50 @@
 use crate::camera::add_camera;
+use crate::checkpoint::RespawnMode;
+use crate::demo::DemoRecorder;
 use crate::draw::PlayerSprite;
-use crate::enemy::{add_enemy, EnemyKind, ParrotKind};
+use crate::files::AssetSource;
 use crate::index::SpatialIndex;
 use crate::level::LevelInfo;
+use crate::liquid::{add_liquid, Liquid, LiquidKind};
 use crate::messages::Messages;
-use crate::physics::{Actor, IntRect, TileBody, TriggerZone};
-use crate::pickup::{add_ammo, add_heart, add_weapon};
+use crate::movement::MovementConfig;
+use crate::objects::{ObjectRegistry, StatCategory};
+use crate::physics::{Actor, IntRect, Ladder, TileBody, TriggerZone};
 use crate::player::Controller;
-use crate::resources::TilesetInfo;
 use crate::resources::{Inventory, SceneResources};
+use crate::resources::{TileAnimation, TilesetInfo};
+use crate::rng::{level_seed, Rng};
 use crate::scene::Scene;
 use crate::script::ScriptEngine;
 use crate::stats::LevelStats;
 use crate::switch::add_switch;
 use crate::visibility::compute_obscurers;
-use crate::weapon::{new_weapon, AmmoType, WeaponSelectorUI, WeaponType};
+use crate::weapon::{new_weapon, WeaponSelectorUI};
 use bitflags::bitflags;
 use hecs::{Entity, World};
 use macroquad::prelude::*;
-use macroquad::{file::load_file, texture::load_texture};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
 use std::io::Cursor;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 bitflags! {
-    pub struct TileFlags: u8 {
+    pub struct TileFlags: u16 {
         const VISIBLE = 0b00000001;
         const BLOCKER = 0b00000010;
         const OBSCURER = 0b00000100;
         const PLATFORM = 0b00001000;
+        const HAZARD = 0b00010000;
+        const SLIPPERY = 0b00100000;
+        const CONVEYOR_LEFT = 0b01000000;
+        const CONVEYOR_RIGHT = 0b10000000;
+        const BOUNCE = 0b1_00000000;
+        const SLOPE_UP = 0b10_00000000;
+        const SLOPE_DOWN = 0b100_00000000;
+        const SLOPE_LOW = 0b1000_00000000;
+        const SLOPE_HIGH = 0b10000_00000000;
+        const LADDER = 0b100000_00000000;
     }
 }
 
@@ -53,22 +68,103 @@ impl TileFlags {
     pub fn is_platform(&self) -> bool {
         self.contains(Self::PLATFORM)
     }
+
+    #[inline]
+    pub fn is_hazard(&self) -> bool {
+        self.contains(Self::HAZARD)
+    }
+
+    #[inline]
+    pub fn is_slippery(&self) -> bool {
+        self.contains(Self::SLIPPERY)
+    }
+
+    #[inline]
+    pub fn is_bounce(&self) -> bool {
+        self.contains(Self::BOUNCE)
+    }
+
+    #[inline]
+    pub fn is_slope(&self) -> bool {
+        self.intersects(Self::SLOPE_UP | Self::SLOPE_DOWN)
+    }
+
+    // the height of a slope's surface above the bottom of the tile at its left and right edges
+    pub fn slope_heights(&self, size: i32) -> Option<(i32, i32)> {
+        let (low, high) = if self.contains(Self::SLOPE_LOW) {
+            (0, size / 2)
+        } else if self.contains(Self::SLOPE_HIGH) {
+            (size / 2, size)
+        } else {
+            (0, size)
+        };
+        if self.contains(Self::SLOPE_UP) {
+            Some((low, high))
+        } else if self.contains(Self::SLOPE_DOWN) {
+            Some((high, low))
+        } else {
+            None
+        }
+    }
+
+    // standing on both kinds of conveyor at once cancels out
+    pub fn conveyor_dir(&self) -> i32 {
+        self.contains(Self::CONVEYOR_RIGHT) as i32 - self.contains(Self::CONVEYOR_LEFT) as i32
+    }
+}
+
+// tile properties that add a behaviour on top of whether the tile is solid
+const TILE_BEHAVIOURS: [(&str, TileFlags); 6] = [
+    ("hazard", TileFlags::HAZARD),
+    ("slippery", TileFlags::SLIPPERY),
+    ("conveyor_left", TileFlags::CONVEYOR_LEFT),
+    ("conveyor_right", TileFlags::CONVEYOR_RIGHT),
+    ("bounce", TileFlags::BOUNCE),
+    ("ladder", TileFlags::LADDER),
+];
+
+// values of the slope property: up slopes rise to the right and down slopes fall to the right,
+// and the 22.5 degree ones come in pairs with a low half and a high half
+fn slope_flags(name: &str) -> Option<TileFlags> {
+    match name {
+        "up" => Some(TileFlags::SLOPE_UP),
+        "down" => Some(TileFlags::SLOPE_DOWN),
+        "up_low" => Some(TileFlags::SLOPE_UP | TileFlags::SLOPE_LOW),
+        "up_high" => Some(TileFlags::SLOPE_UP | TileFlags::SLOPE_HIGH),
+        "down_low" => Some(TileFlags::SLOPE_DOWN | TileFlags::SLOPE_LOW),
+        "down_high" => Some(TileFlags::SLOPE_DOWN | TileFlags::SLOPE_HIGH),
+        _ => None,
+    }
+}
+
+fn tile_behaviours(properties: &tiled::Properties) -> TileFlags {
+    TILE_BEHAVIOURS
+        .iter()
+        .filter(|(name, _)| properties.contains_key(*name))
+        .fold(TileFlags::empty(), |flags, (_, flag)| flags | *flag)
 }
 
 struct AsyncPreloadReader {
+    source: AssetSource,
     cache: HashMap<tiled::ResourcePathBuf, Arc<[u8]>>,
 }
 
 impl AsyncPreloadReader {
-    pub fn new() -> Self {
+    pub fn new(source: AssetSource) -> Self {
         Self {
+            source,
             cache: HashMap::new(),
         }
     }
 
-    pub(crate) async fn preload(&mut self, path: &str) {
-        let data = load_file(path).await.unwrap();
+    pub(crate) async fn preload(&mut self, path: &str) -> Result<(), LoadError> {
+        let data = self
+            .source
+            .load_file(path)
+            .await
+            .map_err(|_| LoadError::MissingFile(path.to_owned()))?;
         self.cache.insert(path.into(), Arc::from(data));
+        Ok(())
     }
 }
 
@@ -83,45 +179,180 @@ impl tiled::ResourceReader for AsyncPreloadReader {
     }
 }
 
-struct LoadingManager {
+#[derive(Clone, Debug)]
+pub enum LoadError {
+    MissingFile(String),
+    BadMap {
+        path: String,
+        message: String,
+    },
+    BadTileset {
+        name: String,
+        reason: String,
+    },
+    BadDataFile {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    UnknownObjectType {
+        obj_type: String,
+        x: i32,
+        y: i32,
+    },
+    ScriptCompile {
+        path: String,
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    // the level loaded but a quicksave couldn't be put into it
+    BadQuicksave(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFile(path) => write!(f, "couldn't load file {}", path),
+            Self::BadMap { path, message } => write!(f, "error in map {}: {}", path, message),
+            Self::BadTileset { name, reason } => write!(f, "bad tileset {}: {}", name, reason),
+            Self::BadDataFile {
+                path,
+                line,
+                message,
+            } => write!(f, "{} line {}: {}", path, line, message),
+            Self::UnknownObjectType { obj_type, x, y } => {
+                write!(f, "unknown object type {} at ({}, {})", obj_type, x, y)
+            }
+            Self::ScriptCompile {
+                path,
+                message,
+                line,
+                column,
+            } => write!(f, "{} line {} column {}: {}", path, line, column, message),
+            Self::BadQuicksave(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+// group layers are flattened, with each layer inheriting any properties it doesn't set itself
+pub(crate) fn flatten_layers<'map>(
+    layers: impl Iterator<Item = tiled::Layer<'map>>,
+    inherited: &tiled::Properties,
+    out: &mut Vec<(tiled::Layer<'map>, tiled::Properties)>,
+) {
+    for layer in layers {
+        let mut properties = inherited.clone();
+        properties.extend(layer.properties.clone());
+        match layer.layer_type() {
+            tiled::LayerType::Group(group) => flatten_layers(group.layers(), &properties, out),
+            _ => out.push((layer, properties)),
+        }
+    }
+}
+
+// the smallest inclusive range of tile coordinates that holds every tile in the layer
+pub(crate) fn tile_bounds(layer_data: &tiled::TileLayer) -> Option<(i32, i32, i32, i32)> {
+    const W: i32 = tiled::ChunkData::WIDTH as i32;
+    const H: i32 = tiled::ChunkData::HEIGHT as i32;
+    // the range of tile coordinates that might contain tiles
+    let (xmin, xmax, ymin, ymax) = match layer_data {
+        tiled::TileLayer::Finite(finite) => (0, finite.width() as i32, 0, finite.height() as i32),
+        tiled::TileLayer::Infinite(infinite) => {
+            let (cx0, cx1, cy0, cy1) = infinite.chunks().fold(
+                (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
+                |(x0, x1, y0, y1), ((x, y), _)| (x0.min(x), x1.max(x), y0.min(y), y1.max(y)),
+            );
+            if cx0 > cx1 {
+                return None;
+            }
+            (cx0 * W, (cx1 + 1) * W, cy0 * H, (cy1 + 1) * H)
+        }
+    };
+    let (mut x0, mut x1, mut y0, mut y1) = (i32::MAX, i32::MIN, i32::MAX, i32::MIN);
+    for y in ymin..ymax {
+        for x in xmin..xmax {
+            if layer_data.get_tile(x, y).is_some() {
+                x0 = x0.min(x);
+                x1 = x1.max(x);
+                y0 = y0.min(y);
+                y1 = y1.max(y);
+            }
+        }
+    }
+    if x0 > x1 {
+        None
+    } else {
+        Some((x0, x1, y0, y1))
+    }
+}
+
+pub(crate) struct LoadingManager {
+    source: AssetSource,
     loader: tiled::Loader<tiled::DefaultResourceCache, AsyncPreloadReader>,
 }
 
 impl LoadingManager {
-    pub fn new() -> Self {
-        let preloader = AsyncPreloadReader::new();
+    pub fn new(source: AssetSource) -> Self {
+        let preloader = AsyncPreloadReader::new(source.clone());
         let loader =
             tiled::Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), preloader);
-        Self { loader }
+        Self { source, loader }
     }
 
-    // eventually this should probably not use String as its error type
-    pub(crate) async fn load_level(
-        &mut self,
-        info: &LevelInfo,
-        inv: Inventory,
-    ) -> Result<Scene, String> {
-        let map_name = format!("{}.tmx", info.path).to_owned();
-        self.loader.reader_mut().preload(&map_name).await;
-
-        let map = loop {
-            match self.loader.load_tmx_map(&map_name) {
-                Ok(map) => break map,
+    // tiled wants to read files synchronously, so keep trying and preload whatever it was missing
+    pub(crate) async fn load_map(&mut self, map_name: &str) -> Result<tiled::Map, LoadError> {
+        self.loader.reader_mut().preload(map_name).await?;
+        loop {
+            match self.loader.load_tmx_map(map_name) {
+                Ok(map) => return Ok(map),
                 Err(tiled::Error::ResourceLoadingError { path, err: _ }) => {
                     if path.as_os_str().to_str().unwrap() == map_name {
-                        return Err("Resource loading error".to_owned());
+                        return Err(LoadError::MissingFile(map_name.to_owned()));
                     }
                     self.loader
                         .reader_mut()
                         .preload(path.as_os_str().to_str().unwrap())
-                        .await;
+                        .await?;
+                }
+                Err(other_err) => {
+                    return Err(LoadError::BadMap {
+                        path: map_name.to_owned(),
+                        message: other_err.to_string(),
+                    })
                 }
-                Err(other_err) => return Err(other_err.to_string()),
+            }
+        }
+    }
+
+    pub(crate) async fn load_level(
+        &mut self,
+        info: &LevelInfo,
+        inv: Inventory,
+        seed: u64,
+    ) -> Result<SceneResources, LoadError> {
+        let map_name = format!("{}.tmx", info.path).to_owned();
+        let map = self.load_map(&map_name).await?;
+        let registry = ObjectRegistry::load(&self.source).await?;
+        let movement = MovementConfig::load(&self.source).await?;
+        let respawn = match map.properties.get("respawn") {
+            None => RespawnMode::Restart,
+            Some(tiled::PropertyValue::StringValue(mode)) => RespawnMode::from_name(mode)
+                .ok_or_else(|| LoadError::BadMap {
+                    path: map_name.clone(),
+                    message: format!("unknown respawn mode {}", mode),
+                })?,
+            Some(_) => {
+                return Err(LoadError::BadMap {
+                    path: map_name.clone(),
+                    message: "respawn should be a string".to_owned(),
+                })
             }
         };
 
         let mut world: World = World::new();
         let mut ids: HashMap<String, Entity> = HashMap::new();
+        let mut attachments: Vec<(Entity, String)> = Vec::new();
         let mut paths: HashMap<String, Vec<(f32, f32)>> = HashMap::new();
         let mut body_index = SpatialIndex::new();
         let mut selector = WeaponSelectorUI::new();
@@ -130,58 +361,79 @@ impl LoadingManager {
         let mut max_items = 0;
         let mut max_secrets = 0;
 
-        if map.tilesets().len() != 1 {
-            return Err("map should contain only one tileset".to_owned());
-        }
-        let ts = &map.tilesets()[0];
-        let texture = load_texture(
-            ts.image
+        // each tile in a body is stored as a global id so that layers can mix tilesets
+        let mut tilesets = Vec::new();
+        let mut first_gid = 1;
+        for ts in map.tilesets() {
+            let image_path = ts
+                .image
                 .as_ref()
-                .ok_or("tileset needs to contain a source filename")?
+                .ok_or_else(|| LoadError::BadTileset {
+                    name: ts.name.clone(),
+                    reason: "tileset needs to contain a source filename".to_owned(),
+                })?
                 .source
                 .as_path()
                 .to_str()
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-        let tiled::Tileset {
-            tile_width,
-            tile_height,
-            columns,
-            ..
-        } = **ts;
-        let tileset_info = TilesetInfo {
-            texture,
-            tile_width,
-            tile_height,
-            columns,
-        };
+                .unwrap();
+            if ts.tile_width != map.tile_width || ts.tile_height != map.tile_height {
+                return Err(LoadError::BadTileset {
+                    name: ts.name.clone(),
+                    reason: "tile size doesn't match the map".to_owned(),
+                });
+            }
+            let texture = self
+                .source
+                .load_texture(image_path)
+                .await
+                .map_err(|_| LoadError::MissingFile(image_path.to_owned()))?;
+            let tiled::Tileset {
+                tile_width,
+                tile_height,
+                columns,
+                tilecount,
+                ..
+            } = **ts;
+            let mut animations = HashMap::new();
+            for (id, tile) in ts.tiles() {
+                match &tile.animation {
+                    Some(frames) if !frames.is_empty() => {
+                        let frames = frames.iter().map(|f| (f.tile_id, f.duration));
+                        animations.insert(id, TileAnimation::from_millis(frames));
+                    }
+                    _ => (),
+                }
+            }
+            tilesets.push(TilesetInfo {
+                texture,
+                tile_width,
+                tile_height,
+                columns,
+                first_gid,
+                animations,
+            });
+            first_gid += tilecount;
+        }
+        if tilesets.is_empty() {
+            return Err(LoadError::BadTileset {
+                name: map_name,
+                reason: "map doesn't contain any tilesets".to_owned(),
+            });
+        }
 
         let mut draw_order = Vec::new();
 
-        for layer in map.layers() {
+        let mut layers = Vec::new();
+        flatten_layers(map.layers(), &tiled::Properties::new(), &mut layers);
+
+        for (layer, properties) in layers {
             match layer.layer_type() {
-                tiled::LayerType::Tiles(tiled::TileLayer::Infinite(layer_data)) => {
-                    let (xmin, xmax, ymin, ymax) = layer_data.chunks().fold(
-                        (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
-                        |(x0, x1, y0, y1), ((x, y), _)| {
-                            (x0.min(x), x1.max(x), y0.min(y), y1.max(y))
-                        },
-                    );
-                    const W: i32 = tiled::ChunkData::WIDTH as i32;
-                    const H: i32 = tiled::ChunkData::HEIGHT as i32;
-                    let (mut x0, mut x1, mut y0, mut y1) = (i32::MAX, i32::MIN, i32::MAX, i32::MIN);
-                    for y in ymin * H..(ymax + 1) * H {
-                        for x in xmin * W..(xmax + 1) * W {
-                            if layer_data.get_tile(x, y).is_some() {
-                                x0 = x0.min(x);
-                                x1 = x1.max(x);
-                                y0 = y0.min(y);
-                                y1 = y1.max(y);
-                            }
-                        }
-                    }
+                tiled::LayerType::Tiles(layer_data) => {
+                    // empty layers are skipped, and reported by the level linter
+                    let (x0, x1, y0, y1) = match tile_bounds(&layer_data) {
+                        Some(bounds) => bounds,
+                        None => continue,
+                    };
                     let mut data = Vec::new();
                     let mut tiles = Vec::new();
                     let mut solid = 0;
@@ -194,7 +446,21 @@ impl LoadingManager {
                                 Some(ltd) => {
                                     // if map parsing is ever slow, we could cache this per tile
                                     let t = ltd.get_tile().unwrap();
-                                    if t.properties.contains_key("background") {
+                                    let flags = if let Some(slope) = t.properties.get("slope") {
+                                        solid += 1;
+                                        let shape = match slope {
+                                            tiled::PropertyValue::StringValue(name) => {
+                                                slope_flags(name)
+                                            }
+                                            _ => None,
+                                        };
+                                        shape.ok_or_else(|| LoadError::BadTileset {
+                                            name: ltd.get_tileset().name.clone(),
+                                            reason: format!("tile {} has a bad slope", ltd.id()),
+                                        })? | TileFlags::VISIBLE
+                                    } else if t.properties.contains_key("background")
+                                        || t.properties.contains_key("ladder")
+                                    {
                                         not_solid += 1;
                                         TileFlags::VISIBLE
                                     } else if t.properties.contains_key("transparent") {
@@ -208,14 +474,17 @@ impl LoadingManager {
                                         TileFlags::BLOCKER
                                             | TileFlags::VISIBLE
                                             | TileFlags::OBSCURER
-                                    }
+                                    };
+                                    flags | tile_behaviours(&t.properties)
                                 }
                             };
                             data.push(td);
-                            tiles.push(t.map(|t| t.id() as u16).unwrap_or(0));
+                            tiles.push(
+                                t.map_or(0, |t| tilesets[t.tileset_index()].first_gid + t.id()),
+                            );
                         }
                     }
-                    let door = layer.properties.contains_key("door");
+                    let door = properties.contains_key("door");
                     let indexed = solid > 0;
                     if solid > 0 && solid < not_solid {
                         println!(
@@ -226,7 +495,7 @@ impl LoadingManager {
                     let body = TileBody::new(
                         x0 * map.tile_width as i32,
                         y0 * map.tile_height as i32,
-                        tileset_info.tile_width as i32,
+                        map.tile_width as i32,
                         (x1 - x0) + 1,
                         data,
                         tiles,
@@ -262,20 +531,39 @@ impl LoadingManager {
                                 shape: tiled::ObjectShape::Rect { width, height },
                                 x,
                                 y,
+                                properties,
                                 ..
                             } => {
-                                let secret = obj_type == "secret";
-                                if secret {
-                                    max_secrets += 1
-                                }
-                                let tz = TriggerZone::new(name.clone(), secret);
                                 let rect = IntRect::new(
                                     *x as i32,
                                     *y as i32,
                                     *width as i32,
                                     *height as i32,
                                 );
-                                world.spawn((tz, rect));
+                                if obj_type == "ladder" {
+                                    world.spawn((Ladder {}, rect));
+                                } else if let Some(kind) = LiquidKind::from_name(obj_type) {
+                                    let id = add_liquid(&mut world, kind, rect);
+                                    match properties.get("attach") {
+                                        None => (),
+                                        Some(tiled::PropertyValue::StringValue(layer)) => {
+                                            attachments.push((id, layer.clone()))
+                                        }
+                                        Some(_) => {
+                                            return Err(LoadError::BadMap {
+                                                path: map_name.clone(),
+                                                message: "attach should be a string".to_owned(),
+                                            })
+                                        }
+                                    }
+                                } else {
+                                    let secret = obj_type == "secret";
+                                    if secret {
+                                        max_secrets += 1
+                                    }
+                                    let tz = TriggerZone::new(name.clone(), secret);
+                                    world.spawn((tz, rect));
+                                }
                             }
                             tiled::ObjectData {
                                 shape: tiled::ObjectShape::Point(x, y),
@@ -290,115 +578,57 @@ impl LoadingManager {
                                         // in case we re-entered the intro level with weapons by looping
                                         selector.hidden = true;
                                     }
-                                } else if obj_type == "enemy" {
-                                    add_enemy(
-                                        &mut world,
-                                        EnemyKind::JumpyDog,
-                                        *x as i32,
-                                        *y as i32,
-                                    );
-                                    max_kills += 1;
-                                } else if obj_type == "walker_enemy" {
-                                    add_enemy(&mut world, EnemyKind::Dog, *x as i32, *y as i32);
-                                    max_kills += 1;
-                                } else if obj_type == "parrot_enemy" {
-                                    add_enemy(
-                                        &mut world,
-                                        EnemyKind::SpiderParrot(ParrotKind::Laser),
-                                        *x as i32,
-                                        *y as i32,
-                                    );
-                                    max_kills += 1;
-                                } else if obj_type == "cannon_parrot_enemy" {
-                                    add_enemy(
-                                        &mut world,
-                                        EnemyKind::SpiderParrot(ParrotKind::Cannon),
-                                        *x as i32,
-                                        *y as i32,
-                                    );
-                                    max_kills += 1;
-                                } else if obj_type == "heart" {
-                                    add_heart(&mut world, *x as i32, *y as i32);
-                                    max_items += 1;
-                                } else if obj_type == "ammo_cells_6" {
-                                    add_ammo(&mut world, *x as i32, *y as i32, AmmoType::Cell, 6);
-                                    max_items += 1;
-                                } else if obj_type == "ammo_shells_4" {
-                                    add_ammo(&mut world, *x as i32, *y as i32, AmmoType::Shell, 4);
-                                    max_items += 1;
-                                } else if obj_type == "ammo_rocket" {
-                                    add_ammo(&mut world, *x as i32, *y as i32, AmmoType::Rocket, 1);
-                                    max_items += 1;
-                                } else if obj_type == "weapon_shotgun" {
-                                    add_weapon(
-                                        &mut world,
-                                        *x as i32,
-                                        *y as i32,
-                                        WeaponType::Shotgun,
-                                    );
-                                    max_items += 1;
-                                } else if obj_type == "weapon_super_shotgun" {
-                                    add_weapon(
-                                        &mut world,
-                                        *x as i32,
-                                        *y as i32,
-                                        WeaponType::SuperShotgun,
-                                    );
-                                    max_items += 1;
-                                } else if obj_type == "weapon_reverse_shotgun" {
-                                    add_weapon(
-                                        &mut world,
-                                        *x as i32,
-                                        *y as i32,
-                                        WeaponType::ReverseShotgun,
-                                    );
-                                    max_items += 1;
-                                } else if obj_type == "weapon_auto_laser" {
-                                    add_weapon(
-                                        &mut world,
-                                        *x as i32,
-                                        *y as i32,
-                                        WeaponType::AutoLaser,
-                                    );
-                                    max_items += 1;
-                                } else if obj_type == "weapon_burst_laser" {
-                                    add_weapon(
-                                        &mut world,
-                                        *x as i32,
-                                        *y as i32,
-                                        WeaponType::BurstLaser,
-                                    );
-                                    max_items += 1;
-                                } else if obj_type == "weapon_double_laser" {
-                                    add_weapon(
-                                        &mut world,
-                                        *x as i32,
-                                        *y as i32,
-                                        WeaponType::DoubleLaser,
-                                    );
-                                    max_items += 1;
                                 } else if obj_type == "switch" {
                                     let id =
                                         add_switch(&mut world, name.clone(), *x as i32, *y as i32);
                                     ids.insert(name.clone(), id);
+                                } else if let Some(spec) = registry.get(obj_type) {
+                                    spec.spawn(&mut world, *x as i32, *y as i32);
+                                    match spec.category {
+                                        StatCategory::Kill => max_kills += 1,
+                                        StatCategory::Item => max_items += 1,
+                                        StatCategory::None => (),
+                                    }
                                 } else {
-                                    println!("found an unknown point object type: {}", obj_type)
+                                    return Err(LoadError::UnknownObjectType {
+                                        obj_type: obj_type.clone(),
+                                        x: *x as i32,
+                                        y: *y as i32,
+                                    });
                                 }
                             }
                             _ => (),
                         }
                     }
                 }
-                _ => println!("found a layer type other than an infinite tiled layer"),
+                _ => println!("found a layer type other than a tile or object layer"),
             }
         }
 
+        // liquids can be attached to layers that come after them in the map
+        for (id, layer) in attachments {
+            let body_id = ids
+                .get(&layer)
+                .copied()
+                .filter(|body_id| world.get::<&TileBody>(*body_id).is_ok())
+                .ok_or_else(|| LoadError::BadMap {
+                    path: map_name.clone(),
+                    message: format!("liquid is attached to {} which is not a tile layer", layer),
+                })?;
+            let body = world.get::<&TileBody>(body_id).unwrap();
+            let rect = world.get::<&IntRect>(id).unwrap();
+            world
+                .get::<&mut Liquid>(id)
+                .unwrap()
+                .attach(&rect, body_id, &body);
+        }
+
         let world_ref = Arc::new(Mutex::new(world));
         let mut script_engine =
             ScriptEngine::new(Arc::clone(&world_ref), Arc::new(ids), Arc::new(paths));
         script_engine
-            .load_file(&format!("{}.rhai", info.path))
-            .await;
+            .load_file(&self.source, &format!("{}.rhai", info.path))
+            .await?;
         script_engine.call_entry_point("init");
 
         let player_start = (psx, psy);
@@ -409,7 +639,7 @@ impl LoadingManager {
             let player_rect = IntRect::new(player_start.0 - 8, player_start.1 - 24, 14, 24);
             let player_eye = player_rect.centre();
             let camera_pos = add_camera(&mut world, player_rect.centre());
-            let player = Actor::new(&player_rect, 0.6);
+            let player = Actor::new(&player_rect, movement.drag);
             let controller = Controller::new();
             let sprite = PlayerSprite::new();
             let player_id = world.spawn((player_rect, player, controller, sprite));
@@ -420,6 +650,7 @@ impl LoadingManager {
         compute_obscurers(&mut world_ref.lock().unwrap());
 
         let stats = LevelStats::new(info.clone(), max_kills, max_items, max_secrets);
+        let history = DemoRecorder::new(&info.path, seed, inv.clone());
         let mut weapons = VecDeque::with_capacity(4);
         for t in inv.weapon_types {
             weapons.push_back(new_weapon(t));
@@ -433,19 +664,44 @@ impl LoadingManager {
             camera_pos,
             draw_order,
             body_index,
-            tileset_info,
+            tilesets,
             messages: Messages::new(),
             stats,
-            triggers: HashSet::new(),
+            // ordered so that entry points always run in the same order
+            triggers: BTreeSet::new(),
             weapons,
             ammo: inv.ammo,
             selector,
             death_timer: None,
+            seed,
+            rng: Rng::new(level_seed(&info.path, seed)),
+            cosmetic_rng: Rng::new(!level_seed(&info.path, seed)),
+            movement,
+            respawn,
+            checkpoint: None,
+            history,
         };
-        Ok(Scene::PlayLevel(resources))
+        Ok(resources)
     }
 }
 
-pub async fn load_level(info: LevelInfo, inv: Inventory) -> Result<Scene, String> {
-    LoadingManager::new().load_level(&info, inv).await
+pub async fn load_level(info: LevelInfo, inv: Inventory, seed: u64) -> Scene {
+    match load_level_resources(AssetSource::Macroquad, info.clone(), inv.clone(), seed).await {
+        Ok(resources) => Scene::PlayLevel(resources),
+        Err(err) => {
+            eprintln!("Couldn't load level {}: {}", info.path, err);
+            Scene::LoadError(info, inv, err)
+        }
+    }
+}
+
+pub async fn load_level_resources(
+    source: AssetSource,
+    info: LevelInfo,
+    inv: Inventory,
+    seed: u64,
+) -> Result<SceneResources, LoadError> {
+    LoadingManager::new(source)
+        .load_level(&info, inv, seed)
+        .await
 }
@@ -1,57 +1,30 @@
-use std::num::NonZeroU8;
+#[cfg(debug_assertions)]
+use enum_iterator::all;
+use std::path::Path;
 
-use camera::PlayerCamera;
-use enemy::update_enemies;
-use hecs::CommandBuffer;
-use input::Input;
 use macroquad::experimental::coroutines::{start_coroutine, stop_all_coroutines};
 use macroquad::prelude::*;
-use physics::{Actor, PathMotion};
-use pickup::{Pickup, WeaponPickup};
-use player::Controller;
-use profile::{Phase, Profiler};
-use projectile::Projectile;
-use render::Renderer;
-use resources::{load_assets, Inventory};
-use scene::{new_prelevel, Scene};
-use timer::Timer;
-use transition::TransitionEffectType;
-use vfx::update_vfx;
-
+use princess_robot::demo::{Demo, DemoPlayer};
+use princess_robot::input::{Input, VirtualKey};
+use princess_robot::lint::check_level;
+use princess_robot::loader::LoadError;
 #[cfg(debug_assertions)]
-use enum_iterator::all;
+use princess_robot::player::Controller;
+use princess_robot::profile::Profiler;
+use princess_robot::quicksave::{save_quicksave, Quicksave};
+use princess_robot::render::Renderer;
 #[cfg(debug_assertions)]
-use input::VirtualKey;
+use princess_robot::resources::SceneResources;
+use princess_robot::resources::{load_assets, Inventory};
+use princess_robot::rng::Rng;
+use princess_robot::save::{CampaignSave, Storage};
+use princess_robot::scene::{new_prelevel, Scene};
+use princess_robot::sim::{step, StepOutcome};
+use princess_robot::timer::Timer;
+use princess_robot::transition::TransitionEffectType;
 #[cfg(debug_assertions)]
-use weapon::{add_ammo, AmmoType};
-
-mod camera;
-mod draw;
-mod enemy;
-mod index;
-mod input;
-mod level;
-mod loader;
-mod messages;
-mod physics;
-mod pickup;
-mod player;
-mod profile;
-mod projectile;
-mod render;
-mod resources;
-mod scene;
-mod script;
-mod stats;
-mod switch;
-mod timer;
-mod transition;
-mod vfx;
-mod visibility;
-mod weapon;
-
-pub(crate) const RENDER_W: u32 = 320;
-pub(crate) const RENDER_H: u32 = 200;
+use princess_robot::weapon::{add_ammo, AmmoType};
+use princess_robot::{RENDER_H, RENDER_W};
 
 fn window_conf() -> Conf {
     Conf {
@@ -63,10 +36,85 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf())]
-async fn main() {
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // checking a level doesn't need a window, so do it before starting macroquad
+    if let Some(idx) = args.iter().position(|a| a == "--check") {
+        let path = args.get(idx + 1).expect("--check needs a level");
+        std::process::exit(check(path));
+    }
+    macroquad::Window::from_config(window_conf(), game(args));
+}
+
+// cheats for trying out levels, kept out of step so that demos and quicksaves play the same
+// in debug and release builds, returning whether to skip to the end of the level
+#[cfg(debug_assertions)]
+fn apply_cheats(resources: &mut SceneResources, input: &Input) -> bool {
+    let world = resources.world_ref.lock().unwrap();
+    if let Ok(mut controller) = world.get::<&mut Controller>(resources.player_id) {
+        if input.is_pressed(VirtualKey::DebugKill) {
+            controller.hp = 0;
+        }
+        if input.is_down(VirtualKey::DebugGodMode) && controller.enable_god_mode() {
+            resources.messages.add("God mode enabled!".to_owned());
+        }
+    }
+    if input.is_pressed(VirtualKey::DebugAmmo) {
+        for typ in all::<AmmoType>() {
+            add_ammo(
+                &mut resources.weapons,
+                &mut resources.ammo,
+                &mut resources.selector,
+                typ,
+                5,
+            );
+        }
+    }
+    input.is_pressed(VirtualKey::DebugWin)
+}
+
+fn check(path: &str) -> i32 {
+    match check_level(Path::new("assets"), path) {
+        Ok(problems) if problems.is_empty() => {
+            println!("{}: no problems found", path);
+            0
+        }
+        Ok(problems) => {
+            for p in problems {
+                println!("{}", p);
+            }
+            1
+        }
+        Err(err) => {
+            println!("{}: {}", path, err);
+            1
+        }
+    }
+}
+
+async fn game(args: Vec<String>) {
     set_pc_assets_folder("assets");
-    let argv: Vec<String> = std::env::args().collect();
+    let mut level_arg = None;
+    let mut seed = 0;
+    let mut record_path = None;
+    let mut demo = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            seed = args
+                .next()
+                .and_then(|s| s.parse().ok())
+                .expect("--seed needs a number");
+        } else if arg == "--record" {
+            record_path = Some(args.next().expect("--record needs a filename"));
+        } else if arg == "--play" {
+            let path = args.next().expect("--play needs a filename");
+            let text = std::fs::read_to_string(&path).expect("couldn't read demo");
+            demo = Some(Demo::parse(&text).unwrap_or_else(|e| panic!("{}", e)));
+        } else {
+            level_arg = Some(arg);
+        }
+    }
 
     let mut renderer = Renderer::new(RENDER_W, RENDER_H);
     let mut clock = Timer::new();
@@ -89,103 +137,181 @@ async fn main() {
     }
     let mut assets = result.unwrap();
 
-    let info = if argv.len() > 1 {
-        assets.get_level_with_path(&argv[1])
-    } else {
-        assets.get_first_level()
+    let mut storage = Storage::for_platform();
+    let mut campaign = CampaignSave::load(&storage).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        CampaignSave::new()
+    });
+
+    let mut inv = Inventory::new();
+    if let Some(demo) = &demo {
+        level_arg = Some(demo.level.clone());
+        seed = demo.seed;
+        inv = demo.inventory.clone();
+    }
+    // transitions happen between levels as well as in them, so they get a generator of their own
+    let mut transition_rng = Rng::new(seed);
+    // only offer to continue if the player didn't ask for somewhere in particular
+    let offer_continue = level_arg.is_none();
+    let info = match level_arg {
+        Some(path) => assets.get_level_with_path(&path),
+        None => assets.get_first_level(),
     };
 
-    let mut scene: Scene = new_prelevel(info, Inventory::new(), false).await;
+    // demos only cover the first attempt at the first level
+    let mut playback = demo.map(DemoPlayer::new);
+    if record_path.is_some() {
+        prevent_quit();
+    }
+    // where to go back to if a level outside the campaign fails to load
+    let mut last_played = None;
+    // a quicksave waiting for its level to finish loading
+    let mut pending_quickload: Option<Quicksave> = None;
+
+    let mut scene: Scene = match &campaign.level {
+        Some(path) if offer_continue => {
+            Scene::Continue(assets.get_level_with_path(path), campaign.inventory.clone())
+        }
+        _ => new_prelevel(info, inv, seed, false).await,
+    };
 
     loop {
+        if is_quit_requested() {
+            if let (Some(path), Scene::PlayLevel(resources)) = (record_path.take(), &scene) {
+                save_demo(&path, resources.history.snapshot());
+            }
+            return;
+        }
+
         match assets.next_scene {
             None => (),
             Some((next_scene, typ)) => {
+                if let Scene::PlayLevel(resources) = &scene {
+                    if let Some(path) = record_path.take() {
+                        save_demo(&path, resources.history.snapshot());
+                    }
+                    playback = None;
+                }
                 clock = Timer::new();
                 input = Input::new();
-                renderer.start_transition(typ);
+                renderer.start_transition(typ, &mut transition_rng);
                 scene = next_scene;
                 assets.next_scene = None;
+                let mut failed_quickload = None;
+                match &mut scene {
+                    Scene::PlayLevel(resources) => {
+                        last_played = Some(resources.stats.info.clone());
+                        if let Some(save) = pending_quickload.take() {
+                            if let Err(msg) = save.apply(resources) {
+                                eprintln!("{}", msg);
+                                failed_quickload = Some((resources.stats.info.clone(), save, msg));
+                            }
+                        }
+                    }
+                    // on the way to the level being loaded
+                    Scene::PreLevel(..) => (),
+                    // the level didn't load, so there's nothing for the quicksave to go into
+                    _ => pending_quickload = None,
+                }
+                // the level is only partly restored, so stop with the error until it's reloaded
+                if let Some((info, save, msg)) = failed_quickload {
+                    scene = Scene::LoadError(info, save.inventory, LoadError::BadQuicksave(msg));
+                }
             }
         }
 
         input.update();
 
         match &mut scene {
+            Scene::Continue(info, inv) => {
+                for _ in 0..clock.get_num_updates() {
+                    renderer.tick();
+                }
+                if input.is_pressed(VirtualKey::Jump) {
+                    assets.next_scene = Some((
+                        new_prelevel(info.clone(), inv.clone(), seed, false).await,
+                        TransitionEffectType::Shatter,
+                    ));
+                } else if input.is_pressed(VirtualKey::Fire) {
+                    assets.next_scene = Some((
+                        new_prelevel(assets.get_first_level(), Inventory::new(), seed, false).await,
+                        TransitionEffectType::Shatter,
+                    ));
+                }
+            }
             Scene::PreLevel(_n, coro, fast) => {
                 for _ in 0..clock.get_num_updates() {
                     renderer.tick();
                 }
                 if (*fast || renderer.transition_finished()) && coro.is_done() {
-                    assets.next_scene = Some((
-                        coro.retrieve().unwrap().unwrap(),
-                        TransitionEffectType::Open,
-                    ))
+                    assets.next_scene = Some((coro.retrieve().unwrap(), TransitionEffectType::Open))
                 }
             }
             Scene::PlayLevel(ref mut resources) => {
                 for _ in 0..clock.get_num_updates() {
-                    let mut buffer = CommandBuffer::new();
-                    profiler.start(Phase::Motion);
-                    PathMotion::apply(resources);
-                    profiler.start(Phase::Pickups);
-                    Pickup::update(resources, &mut buffer);
-                    WeaponPickup::update(resources);
-                    profiler.start(Phase::Player);
-                    Controller::update(resources, &mut buffer, &input);
-                    profiler.start(Phase::Enemies);
-                    update_enemies(resources, &mut buffer);
-                    profiler.start(Phase::Actor);
-                    Actor::update(resources);
-                    profiler.start(Phase::Projectile);
-                    Projectile::update(resources, &mut buffer);
-                    profiler.start(Phase::Vfx);
-                    update_vfx(resources, &mut buffer);
-                    profiler.stop();
-                    buffer.run_on(&mut resources.world_ref.lock().unwrap());
-
-                    PlayerCamera::update(resources);
-
-                    let mut player_dead = true;
-                    {
-                        let w = resources.world_ref.lock().unwrap();
-                        #[allow(unused_mut)] // needs to be mut in debug mode but not release
-                        if let Ok(mut controller) = w.get::<&mut Controller>(resources.player_id) {
-                            #[cfg(debug_assertions)]
-                            if input.is_pressed(VirtualKey::DebugKill) {
-                                controller.hp = 0
-                            }
-                            player_dead = controller.hp == 0;
-                        };
+                    if let Some(player) = &mut playback {
+                        player.play_input(&mut input);
+                    }
+                    #[cfg(debug_assertions)]
+                    let cheated_win = apply_cheats(resources, &input);
+                    let outcome = step(resources, &input, &mut profiler);
+                    #[cfg(debug_assertions)]
+                    let outcome = if cheated_win {
+                        StepOutcome::Won
+                    } else {
+                        outcome
                     };
-                    if player_dead {
-                        let dt = &mut resources
-                            .death_timer
-                            .get_or_insert(NonZeroU8::new(1).unwrap());
-                        **dt = dt.saturating_add(1);
-                        let n = dt.get();
-                        if n == 60 {
-                            resources
-                                .messages
-                                .add("Press any key to restart.".to_owned());
+                    if let Some(player) = &mut playback {
+                        if let Err(msg) = player.check_state(resources) {
+                            eprintln!("{}", msg);
+                            resources.messages.add(msg);
                         }
-                        if n > 30 && input.is_any_pressed() {
-                            stop_all_coroutines();
-                            assets.next_scene = Some((
-                                new_prelevel(resources.stats.info.clone(), Inventory::new(), false)
-                                    .await,
-                                TransitionEffectType::Shatter,
-                            ));
+                        if player.is_finished() {
+                            println!("Demo finished after {} ticks", resources.stats.frames);
+                            playback = None;
                         }
                     }
 
-                    for t in &resources.triggers {
-                        resources.script_engine.call_entry_point(t);
+                    if outcome == StepOutcome::Respawned {
+                        renderer
+                            .start_transition(TransitionEffectType::Shatter, &mut transition_rng);
+                    } else if outcome == StepOutcome::Restart {
+                        stop_all_coroutines();
+                        assets.next_scene = Some((
+                            new_prelevel(
+                                resources.stats.info.clone(),
+                                Inventory::new(),
+                                seed,
+                                false,
+                            )
+                            .await,
+                            TransitionEffectType::Shatter,
+                        ));
+                    }
+
+                    if input.is_pressed(VirtualKey::QuickSave) {
+                        let msg = match save_quicksave(resources, &mut storage) {
+                            Ok(()) => "Game saved.".to_owned(),
+                            Err(e) => format!("Couldn't save: {}", e),
+                        };
+                        resources.messages.add(msg);
                     }
-                    resources.triggers.clear();
-                    resources.script_engine.schedule_queued_funcs();
-                    for m in resources.script_engine.new_popups() {
-                        resources.messages.add(m);
+                    if input.is_pressed(VirtualKey::QuickLoad) {
+                        match Quicksave::load(&storage) {
+                            Ok(Some(save)) => {
+                                stop_all_coroutines();
+                                let info = assets.get_level_with_path(&save.level);
+                                let inv = save.inventory.clone();
+                                let save_seed = save.seed;
+                                pending_quickload = Some(save);
+                                assets.next_scene = Some((
+                                    new_prelevel(info, inv, save_seed, true).await,
+                                    TransitionEffectType::Shatter,
+                                ));
+                            }
+                            Ok(None) => resources.messages.add("No quicksave yet.".to_owned()),
+                            Err(e) => resources.messages.add(format!("Couldn't load: {}", e)),
+                        }
                     }
 
                     #[cfg(debug_assertions)]
@@ -193,47 +319,47 @@ async fn main() {
                         show_profile = !show_profile;
                     }
                     #[cfg(debug_assertions)]
-                    if input.is_pressed(VirtualKey::DebugAmmo) {
-                        for typ in all::<AmmoType>() {
-                            add_ammo(
-                                &mut resources.weapons,
-                                &mut resources.ammo,
-                                &mut resources.selector,
-                                typ,
-                                5,
-                            );
-                        }
-                    }
-                    #[cfg(debug_assertions)]
                     if input.is_pressed(VirtualKey::DebugRestart) {
                         stop_all_coroutines();
                         assets.next_scene = Some((
                             // skip the transition for faster debugging
-                            new_prelevel(resources.stats.info.clone(), Inventory::new(), true)
-                                .await,
+                            new_prelevel(
+                                resources.stats.info.clone(),
+                                Inventory::new(),
+                                seed,
+                                true,
+                            )
+                            .await,
                             TransitionEffectType::Shatter,
                         ));
                     }
-                    #[cfg(debug_assertions)]
-                    let won = input.is_pressed(VirtualKey::DebugWin)
-                        || resources.script_engine.win_flag();
-                    #[cfg(not(debug_assertions))]
-                    let won = resources.script_engine.win_flag();
-                    if won {
+                    if outcome == StepOutcome::Won {
+                        // only levels from levels.txt are part of the campaign
+                        let mut previous = None;
+                        if resources.stats.info.number.is_some() {
+                            previous = campaign.records.get(&resources.stats.info.path).copied();
+                            let next = assets.get_next_level(&resources.stats.info);
+                            campaign.complete_level(
+                                &resources.stats,
+                                &next.path,
+                                resources.persist_inventory(),
+                            );
+                            if let Err(e) = campaign.save(&mut storage) {
+                                eprintln!("{}", e);
+                            }
+                        }
                         stop_all_coroutines();
                         assets.next_scene = Some((
-                            crate::scene::Scene::PostLevel(
+                            Scene::PostLevel(
                                 resources.stats.clone(),
                                 resources.persist_inventory(),
+                                previous,
                             ),
                             TransitionEffectType::Shatter,
                         ));
                     }
 
                     input.reset();
-                    resources.messages.update();
-                    resources.selector.update();
-                    resources.stats.frames += 1;
                     renderer.tick();
 
                     /* if resources.stats.frames % 100 == 0 {
@@ -241,14 +367,41 @@ async fn main() {
                     } */
                 }
             }
-            Scene::PostLevel(stats, inv) => {
+            Scene::LoadError(info, inv, _) => {
+                for _ in 0..clock.get_num_updates() {
+                    renderer.tick();
+                }
+                if input.is_pressed(VirtualKey::Jump) {
+                    assets.next_scene = Some((
+                        new_prelevel(info.clone(), inv.clone(), seed, false).await,
+                        TransitionEffectType::Shatter,
+                    ));
+                } else if input.is_pressed(VirtualKey::Fire) {
+                    // there's nothing before the first level to go back to
+                    let previous = match info.number {
+                        Some(_) => assets.get_previous_level(info),
+                        None => Some(
+                            last_played
+                                .clone()
+                                .unwrap_or_else(|| assets.get_first_level()),
+                        ),
+                    };
+                    if let Some(info) = previous {
+                        assets.next_scene = Some((
+                            new_prelevel(info, inv.clone(), seed, false).await,
+                            TransitionEffectType::Shatter,
+                        ));
+                    }
+                }
+            }
+            Scene::PostLevel(stats, inv, _) => {
                 for _ in 0..clock.get_num_updates() {
                     renderer.tick();
                 }
                 if input.is_any_pressed() {
                     let info = assets.get_next_level(&stats.info);
                     assets.next_scene = Some((
-                        new_prelevel(info, inv.clone(), false).await,
+                        new_prelevel(info, inv.clone(), seed, false).await,
                         TransitionEffectType::Shatter,
                     ));
                 }
@@ -263,3 +416,10 @@ async fn main() {
         next_frame().await;
     }
 }
+
+fn save_demo(path: &str, demo: Demo) {
+    match std::fs::write(path, demo.to_text()) {
+        Ok(()) => println!("Saved demo to {}", path),
+        Err(e) => eprintln!("Couldn't save demo to {}: {}", path, e),
+    }
+}
@@ -23,7 +23,7 @@ impl Messages {
 
     pub fn update(&mut self) {
         self.entries.iter_mut().for_each(|t| t.0 -= 1);
-        if self.entries.front().map_or(false, |t| t.0 <= 0) {
+        if self.entries.front().is_some_and(|t| t.0 <= 0) {
             self.offset += 2;
         }
         if self.offset >= Self::HEIGHT {
@@ -0,0 +1,185 @@
+use crate::files::AssetSource;
+use crate::liquid::LiquidKind;
+use crate::loader::LoadError;
+use std::str::FromStr;
+
+pub const MOVEMENT_FILE: &str = "movement.txt";
+
+// how a liquid slows down everything in it, as multipliers on gravity and drag
+#[derive(Clone, Copy)]
+pub struct LiquidMovement {
+    pub gravity: f32,
+    pub drag_x: f32,
+    pub drag_y: f32,
+    // how fast pressing jump makes the player swim up
+    pub swim_speed: f32,
+}
+
+impl LiquidMovement {
+    fn new(gravity: f32, drag_x: f32, drag_y: f32, swim_speed: f32) -> Self {
+        Self {
+            gravity,
+            drag_x,
+            drag_y,
+            swim_speed,
+        }
+    }
+}
+
+// everything that decides how the player moves, so that it can be tuned without recompiling
+#[derive(Clone)]
+pub struct MovementConfig {
+    pub drag: f32,
+    pub accel: f32,
+    // with the lower drag on slippery ground the default gives the same top speed as normal
+    pub slippery_accel: f32,
+    pub jump_speed: f32,
+    // the speed kept up for the first few frames while jump is held
+    pub jump_hold_speed: f32,
+    pub jump_hold_frames: u32,
+    // how long after leaving the ground a jump still works
+    pub coyote_frames: u32,
+    // how long before landing a jump press is remembered
+    pub jump_buffer_frames: u32,
+    pub climb_speed: i32,
+    pub wall_slide_speed: f32,
+    pub wall_slide_gravity: f32,
+    pub wall_jump_speed: f32,
+    // liquids affect enemies as well as the player
+    pub water: LiquidMovement,
+    pub acid: LiquidMovement,
+    pub lava: LiquidMovement,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            drag: 0.6,
+            accel: 3.0,
+            slippery_accel: 0.5,
+            jump_speed: 6.0,
+            jump_hold_speed: 10.0,
+            jump_hold_frames: 5,
+            coyote_frames: 4,
+            jump_buffer_frames: 4,
+            climb_speed: 2,
+            wall_slide_speed: 2.0,
+            wall_slide_gravity: 0.25,
+            wall_jump_speed: 8.0,
+            water: LiquidMovement::new(0.25, 0.8, 0.85, 4.0),
+            acid: LiquidMovement::new(0.3, 0.7, 0.75, 3.5),
+            lava: LiquidMovement::new(0.15, 0.6, 0.7, 3.0),
+        }
+    }
+}
+
+impl MovementConfig {
+    pub fn liquid(&self, kind: LiquidKind) -> &LiquidMovement {
+        match kind {
+            LiquidKind::Water => &self.water,
+            LiquidKind::Acid => &self.acid,
+            LiquidKind::Lava => &self.lava,
+        }
+    }
+
+    fn liquid_mut(&mut self, kind: LiquidKind) -> &mut LiquidMovement {
+        match kind {
+            LiquidKind::Water => &mut self.water,
+            LiquidKind::Acid => &mut self.acid,
+            LiquidKind::Lava => &mut self.lava,
+        }
+    }
+
+    pub async fn load(source: &AssetSource) -> Result<Self, LoadError> {
+        let text = source
+            .load_string(MOVEMENT_FILE)
+            .await
+            .map_err(|_| LoadError::MissingFile(MOVEMENT_FILE.to_owned()))?;
+        Self::parse(&text).map_err(|(line, message)| LoadError::BadDataFile {
+            path: MOVEMENT_FILE.to_owned(),
+            line,
+            message,
+        })
+    }
+
+    // lines are a name and a value, and anything not mentioned keeps its default.
+    // liquid settings are named after the liquid, like water_gravity
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut config = Self::default();
+        for (idx, line) in text.lines().enumerate() {
+            let err = |what: String| (idx + 1, what);
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (name, value) = match parts[..] {
+                [] => continue,
+                [first, ..] if first.starts_with('#') => continue,
+                [name, value] => (name, value),
+                _ => return Err(err("needs a name and a value".to_owned())),
+            };
+            match name {
+                "drag" => config.drag = parse_value(value).map_err(err)?,
+                "accel" => config.accel = parse_value(value).map_err(err)?,
+                "slippery_accel" => config.slippery_accel = parse_value(value).map_err(err)?,
+                "jump_speed" => config.jump_speed = parse_value(value).map_err(err)?,
+                "jump_hold_speed" => config.jump_hold_speed = parse_value(value).map_err(err)?,
+                "jump_hold_frames" => config.jump_hold_frames = parse_value(value).map_err(err)?,
+                "coyote_frames" => config.coyote_frames = parse_value(value).map_err(err)?,
+                "jump_buffer_frames" => {
+                    config.jump_buffer_frames = parse_value(value).map_err(err)?
+                }
+                "climb_speed" => config.climb_speed = parse_value(value).map_err(err)?,
+                "wall_slide_speed" => config.wall_slide_speed = parse_value(value).map_err(err)?,
+                "wall_slide_gravity" => {
+                    config.wall_slide_gravity = parse_value(value).map_err(err)?
+                }
+                "wall_jump_speed" => config.wall_jump_speed = parse_value(value).map_err(err)?,
+                other => {
+                    let (kind, setting) = other
+                        .split_once('_')
+                        .and_then(|(liquid, setting)| {
+                            Some((LiquidKind::from_name(liquid)?, setting))
+                        })
+                        .ok_or_else(|| err(format!("unknown setting {}", other)))?;
+                    let liquid = config.liquid_mut(kind);
+                    let field = match setting {
+                        "gravity" => &mut liquid.gravity,
+                        "drag_x" => &mut liquid.drag_x,
+                        "drag_y" => &mut liquid.drag_y,
+                        "swim_speed" => &mut liquid.swim_speed,
+                        _ => return Err(err(format!("unknown setting {}", other))),
+                    };
+                    *field = parse_value(value).map_err(err)?;
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad value {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let config = MovementConfig::parse("# tuning\naccel 2.5\ncoyote_frames 0\n").unwrap();
+        assert_eq!((config.accel, config.coyote_frames), (2.5, 0));
+        assert_eq!(config.jump_speed, MovementConfig::default().jump_speed);
+        let config = MovementConfig::parse("acid_drag_x 0.5\nlava_swim_speed 1\n").unwrap();
+        assert_eq!((config.acid.drag_x, config.lava.swim_speed), (0.5, 1.0));
+        assert_eq!(config.water.drag_x, MovementConfig::default().water.drag_x);
+        for bad in [
+            "accel",
+            "accel fast",
+            "coyote_frames 1.5",
+            "gravity 2.0",
+            "water_speed 1.0",
+            "swim_speed 4.0",
+        ] {
+            assert!(MovementConfig::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
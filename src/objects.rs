@@ -0,0 +1,206 @@
+use crate::checkpoint::add_checkpoint;
+use crate::enemy::{add_enemy, EnemyKind, ParrotKind};
+use crate::files::AssetSource;
+use crate::loader::LoadError;
+use crate::pickup::{add_ammo, add_heart, add_wall_jump, add_weapon};
+use crate::weapon::{AmmoQuantity, AmmoType, WeaponType};
+use hecs::World;
+use std::collections::HashMap;
+
+pub const OBJECTS_FILE: &str = "objects.txt";
+
+// object types the loader handles itself because they aren't just spawned into the world
+pub const SPECIAL_OBJECT_TYPES: [&str; 2] = ["player", "switch"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StatCategory {
+    Kill,
+    Item,
+    None,
+}
+
+#[derive(Clone, Copy)]
+pub enum Spawner {
+    // and whether its wreck explodes
+    Enemy(EnemyKind, bool),
+    Heart,
+    Ammo(AmmoType, AmmoQuantity),
+    Weapon(WeaponType),
+    WallJump,
+    Checkpoint,
+}
+
+#[derive(Clone, Copy)]
+pub struct ObjectSpec {
+    pub category: StatCategory,
+    pub spawner: Spawner,
+}
+
+impl ObjectSpec {
+    pub fn spawn(&self, world: &mut World, x: i32, y: i32) {
+        match self.spawner {
+            Spawner::Enemy(kind, exploding) => add_enemy(world, kind, exploding, x, y),
+            Spawner::Heart => add_heart(world, x, y),
+            Spawner::Ammo(typ, amt) => add_ammo(world, x, y, typ, amt),
+            Spawner::Weapon(typ) => add_weapon(world, x, y, typ),
+            Spawner::WallJump => add_wall_jump(world, x, y),
+            Spawner::Checkpoint => add_checkpoint(world, x, y),
+        }
+    }
+}
+
+// what to spawn for each type of point object in a map, read from a file with lines like:
+//   <type> <kill|item|none> enemy <dog|jumpy_dog|laser_parrot|cannon_parrot> [exploding]
+//   <type> <kill|item|none> heart
+//   <type> <kill|item|none> ammo <cell|shell|rocket> <amount>
+//   <type> <kill|item|none> weapon <weapon name>
+//   <type> <kill|item|none> wall_jump
+//   <type> <kill|item|none> checkpoint
+pub struct ObjectRegistry {
+    specs: HashMap<String, ObjectSpec>,
+}
+
+impl ObjectRegistry {
+    pub async fn load(source: &AssetSource) -> Result<Self, LoadError> {
+        let text = source
+            .load_string(OBJECTS_FILE)
+            .await
+            .map_err(|_| LoadError::MissingFile(OBJECTS_FILE.to_owned()))?;
+        Self::parse(&text).map_err(|(line, message)| LoadError::BadDataFile {
+            path: OBJECTS_FILE.to_owned(),
+            line,
+            message,
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut specs = HashMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let err = |what: String| (idx + 1, what);
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (name, category, spawner, args) = match parts[..] {
+                [] => continue,
+                [first, ..] if first.starts_with('#') => continue,
+                [name, category, spawner, ref args @ ..] => (name, category, spawner, args),
+                _ => return Err(err("needs a type, a category and a spawner".to_owned())),
+            };
+            let category = match category {
+                "kill" => StatCategory::Kill,
+                "item" => StatCategory::Item,
+                "none" => StatCategory::None,
+                other => return Err(err(format!("unknown category {}", other))),
+            };
+            let spawner = match (spawner, args) {
+                ("enemy", [kind]) | ("enemy", [kind, "exploding"]) => Spawner::Enemy(
+                    parse_enemy_kind(kind).ok_or_else(|| err(format!("unknown enemy {}", kind)))?,
+                    args.len() == 2,
+                ),
+                ("heart", []) => Spawner::Heart,
+                ("wall_jump", []) => Spawner::WallJump,
+                ("checkpoint", []) => Spawner::Checkpoint,
+                ("ammo", [typ, amt]) => Spawner::Ammo(
+                    parse_ammo_type(typ)
+                        .ok_or_else(|| err(format!("unknown ammo type {}", typ)))?,
+                    amt.parse()
+                        .map_err(|_| err(format!("bad ammo amount {}", amt)))?,
+                ),
+                ("weapon", [typ]) => Spawner::Weapon(
+                    parse_weapon_type(typ).ok_or_else(|| err(format!("unknown weapon {}", typ)))?,
+                ),
+                (other, _) => {
+                    return Err(err(format!(
+                        "unknown spawner {} or wrong number of arguments",
+                        other
+                    )))
+                }
+            };
+            if SPECIAL_OBJECT_TYPES.contains(&name) {
+                return Err(err(format!("{} objects can't be redefined", name)));
+            }
+            if specs
+                .insert(name.to_owned(), ObjectSpec { category, spawner })
+                .is_some()
+            {
+                return Err(err(format!("{} is defined twice", name)));
+            }
+        }
+        Ok(Self { specs })
+    }
+
+    pub fn get(&self, obj_type: &str) -> Option<&ObjectSpec> {
+        self.specs.get(obj_type)
+    }
+
+    pub fn is_known(&self, obj_type: &str) -> bool {
+        SPECIAL_OBJECT_TYPES.contains(&obj_type) || self.specs.contains_key(obj_type)
+    }
+}
+
+fn parse_enemy_kind(name: &str) -> Option<EnemyKind> {
+    match name {
+        "dog" => Some(EnemyKind::Dog),
+        "jumpy_dog" => Some(EnemyKind::JumpyDog),
+        "laser_parrot" => Some(EnemyKind::SpiderParrot(ParrotKind::Laser)),
+        "cannon_parrot" => Some(EnemyKind::SpiderParrot(ParrotKind::Cannon)),
+        _ => None,
+    }
+}
+
+fn parse_ammo_type(name: &str) -> Option<AmmoType> {
+    match name {
+        "cell" => Some(AmmoType::Cell),
+        "shell" => Some(AmmoType::Shell),
+        "rocket" => Some(AmmoType::Rocket),
+        _ => None,
+    }
+}
+
+fn parse_weapon_type(name: &str) -> Option<WeaponType> {
+    match name {
+        "shotgun" => Some(WeaponType::Shotgun),
+        "super_shotgun" => Some(WeaponType::SuperShotgun),
+        "reverse_shotgun" => Some(WeaponType::ReverseShotgun),
+        "auto_laser" => Some(WeaponType::AutoLaser),
+        "burst_laser" => Some(WeaponType::BurstLaser),
+        "double_laser" => Some(WeaponType::DoubleLaser),
+        "rocket_launcher" => Some(WeaponType::RocketLauncher),
+        "charge_laser" => Some(WeaponType::ChargeLaser),
+        "beam_laser" => Some(WeaponType::BeamLaser),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_registry() {
+        let registry =
+            ObjectRegistry::parse("ammo_cells_12 item ammo cell 12\ncheckpoint none checkpoint\n")
+                .unwrap();
+        assert!(registry.get("ammo_cells_12").unwrap().category == StatCategory::Item);
+        assert!(matches!(
+            registry.get("checkpoint").unwrap().spawner,
+            Spawner::Checkpoint
+        ));
+        assert!(registry.is_known("player"));
+        let registry = ObjectRegistry::parse("boom kill enemy dog exploding").unwrap();
+        assert!(matches!(
+            registry.get("boom").unwrap().spawner,
+            Spawner::Enemy(EnemyKind::Dog, true)
+        ));
+        assert!(!registry.is_known("ammo_cells_6"));
+        for bad in [
+            "heart item",
+            "heart treasure heart",
+            "heart item heart heart",
+            "dog kill enemy cat",
+            "dog kill enemy dog sparkly",
+            "player none heart",
+            "heart item heart\nheart item heart",
+        ] {
+            assert!(ObjectRegistry::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
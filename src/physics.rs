@@ -1,4 +1,5 @@
 use crate::index::SpatialIndex;
+use crate::liquid::{liquid_at, liquid_volumes, LiquidKind};
 use crate::loader::TileFlags;
 use crate::resources::SceneResources;
 use hecs::{Entity, World};
@@ -38,6 +39,41 @@ impl IntRect {
     pub fn centre_int(&self) -> (i32, i32) {
         (self.x + self.w / 2, self.y + self.h / 2)
     }
+
+    // how far along a ray it is to the edge of the rect, if the ray meets it within max_dist
+    pub fn ray_distance(&self, from: Vec2, dir: Vec2, max_dist: f32) -> Option<f32> {
+        let mut t_min = 0.0_f32;
+        let mut t_max = max_dist;
+        for (p, d, lo, hi) in [
+            (from.x, dir.x, self.x, self.x + self.w),
+            (from.y, dir.y, self.y, self.y + self.h),
+        ] {
+            let (lo, hi) = (lo as f32, hi as f32);
+            if d == 0.0 {
+                if p < lo || p >= hi {
+                    return None;
+                }
+            } else {
+                let (a, b) = ((lo - p) / d, (hi - p) / d);
+                t_min = t_min.max(a.min(b));
+                t_max = t_max.min(a.max(b));
+            }
+        }
+        if t_min <= t_max {
+            Some(t_min)
+        } else {
+            None
+        }
+    }
+}
+
+fn rect_between(a: Vec2, b: Vec2) -> IntRect {
+    IntRect::new(
+        a.x.min(b.x).floor() as i32,
+        a.y.min(b.y).floor() as i32,
+        (a.x - b.x).abs().ceil() as i32 + 1,
+        (a.y - b.y).abs().ceil() as i32 + 1,
+    )
 }
 
 fn pushing_rect(rect: &IntRect) -> IntRect {
@@ -82,11 +118,14 @@ impl TriggerZone {
     }
 }
 
+// a climbable area that isn't made of ladder tiles
+pub struct Ladder {}
+
 pub struct TileBody {
     pub width: i32,
     pub size: i32,
     pub data: Vec<TileFlags>,
-    pub tiles: Vec<u16>,
+    pub tiles: Vec<u32>,
     pub x: i32,
     pub y: i32,
     pub base_pos: Vec2,
@@ -95,13 +134,14 @@ pub struct TileBody {
 }
 
 impl TileBody {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         x: i32,
         y: i32,
         size: i32,
         width: i32,
         data: Vec<TileFlags>,
-        tiles: Vec<u16>,
+        tiles: Vec<u32>,
         door: bool,
         indexed: bool,
     ) -> Self {
@@ -141,13 +181,17 @@ impl TileBody {
                 for kx in min_kx..=max_kx {
                     if kx >= 0 && kx < self.width {
                         let index = ky * self.width + kx;
-                        if index >= 0
-                            && index < self.data.len() as i32
-                            && (self.data[index as usize].is_blocker()
-                                || self.data[index as usize].is_platform()
-                                    && typ == CollisionType::TopOfBlockerOrPlatform)
-                        {
-                            return true;
+                        if index >= 0 && index < self.data.len() as i32 {
+                            let f = self.data[index as usize];
+                            if f.is_blocker()
+                                || f.is_platform() && typ == CollisionType::TopOfBlockerOrPlatform
+                            {
+                                return true;
+                            }
+                            // move_actor lets actors walk onto slopes without going through here
+                            if typ == CollisionType::Blocker && self.under_slope(rect, kx, ky, f) {
+                                return true;
+                            }
                         }
                     }
                 }
@@ -156,6 +200,164 @@ impl TileBody {
         false
     }
 
+    // the combined flags of the tiles a rect overlaps, or if standing is set, only of the
+    // solid tiles whose tops it is touching (as in the TopOfBlockerOrPlatform collision)
+    fn flags_overlapping(&self, rect: &IntRect, standing: bool) -> TileFlags {
+        let adjustment = if standing { self.size - 1 } else { 0 };
+        let min_kx = (rect.x - self.x).div_euclid(self.size).max(0);
+        let max_kx = (rect.x + rect.w - 1 - self.x)
+            .div_euclid(self.size)
+            .min(self.width - 1);
+        let min_ky = (rect.y - self.y + adjustment).div_euclid(self.size).max(0);
+        let max_ky = (rect.y + rect.h - 1 - self.y).div_euclid(self.size);
+        let mut flags = TileFlags::empty();
+        for ky in min_ky..=max_ky {
+            for kx in min_kx..=max_kx {
+                let index = ky * self.width + kx;
+                if index < self.data.len() as i32 {
+                    let f = self.data[index as usize];
+                    if !standing || f.is_blocker() || f.is_platform() {
+                        flags |= f;
+                    }
+                }
+            }
+        }
+        flags
+    }
+
+    // how far along a ray it is to the first tile that passes the test (or the surface of a slope),
+    // stepping from one tile boundary to the next so no tile can be skipped over
+    fn ray_distance(
+        &self,
+        from: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        hits: impl Fn(TileFlags) -> bool,
+    ) -> Option<f32> {
+        let size = self.size as f32;
+        let height = self.data.len() as i32 / self.width;
+        let local = (from - vec2(self.x as f32, self.y as f32)) / size;
+        let mut kx = local.x.floor() as i32;
+        let mut ky = local.y.floor() as i32;
+        // which way to step, the distance to the first boundary and the distance between them
+        let axis = |p: f32, k: i32, d: f32| match d.total_cmp(&0.0) {
+            Ordering::Greater => (1, ((k + 1) as f32 - p) * size / d, size / d),
+            Ordering::Less => (-1, (p - k as f32) * size / -d, size / -d),
+            Ordering::Equal => (0, f32::INFINITY, f32::INFINITY),
+        };
+        let (step_x, mut next_x, delta_x) = axis(local.x, kx, dir.x);
+        let (step_y, mut next_y, delta_y) = axis(local.y, ky, dir.y);
+        let mut t = 0.0;
+        while t <= max_dist {
+            if kx >= 0 && kx < self.width && ky >= 0 && ky < height {
+                let f = self.data[(ky * self.width + kx) as usize];
+                // slopes are solid to everything, but only below their surface
+                if let Some(heights) = f.slope_heights(self.size) {
+                    let t_exit = next_x.min(next_y).min(max_dist);
+                    let tile = (kx, ky);
+                    if let Some(d) = self.ray_under_slope(from, dir, tile, heights, t, t_exit) {
+                        return Some(d);
+                    }
+                } else if hits(f) {
+                    return Some(t);
+                }
+            }
+            if next_x < next_y {
+                t = next_x;
+                next_x += delta_x;
+                kx += step_x;
+            } else {
+                t = next_y;
+                next_y += delta_y;
+                ky += step_y;
+            }
+        }
+        None
+    }
+
+    // where a ray between two distances first goes below the surface of a slope tile,
+    // which is easy to find because the surface is a straight line
+    fn ray_under_slope(
+        &self,
+        from: Vec2,
+        dir: Vec2,
+        (kx, ky): (i32, i32),
+        (left, right): (i32, i32),
+        t0: f32,
+        t1: f32,
+    ) -> Option<f32> {
+        let size = self.size as f32;
+        let tile_x = (self.x + kx * self.size) as f32;
+        let bottom = (self.y + (ky + 1) * self.size) as f32;
+        // how far below the surface the ray is at a distance along it
+        let below = |t: f32| {
+            let p = from + dir * t;
+            let height = left as f32 + (right - left) as f32 * (p.x - tile_x) / size;
+            p.y - (bottom - height)
+        };
+        let (g0, g1) = (below(t0), below(t1));
+        if g0 >= 0.0 {
+            Some(t0)
+        } else if g1 >= 0.0 {
+            Some(t0 + (t1 - t0) * g0 / (g0 - g1))
+        } else {
+            None
+        }
+    }
+
+    // the height of a slope tile's surface above the bottom of the tile at its highest point
+    // over the columns a rect covers
+    fn slope_height(&self, rect: &IntRect, kx: i32, (left, right): (i32, i32)) -> i32 {
+        let tile_x = self.x + kx * self.size;
+        let px0 = (rect.x - tile_x).max(0);
+        let px1 = (rect.x + rect.w - tile_x).min(self.size);
+        // the surface is a straight line so its highest point is at one end
+        (left + (right - left) * px0 / self.size).max(left + (right - left) * px1 / self.size)
+    }
+
+    // whether a rect reaches below the surface of a slope tile, so that an actor standing
+    // on the slope doesn't count as overlapping it
+    fn under_slope(&self, rect: &IntRect, kx: i32, ky: i32, f: TileFlags) -> bool {
+        match f.slope_heights(self.size) {
+            Some(heights) => {
+                let height = self.slope_height(rect, kx, heights);
+                rect.y + rect.h > self.y + (ky + 1) * self.size - height
+            }
+            None => false,
+        }
+    }
+
+    // the highest point of any slope under the rect that is between y_min and y_max,
+    // along with the flags of the tile it's part of
+    fn slope_surface(&self, rect: &IntRect, y_min: i32, y_max: i32) -> Option<(i32, TileFlags)> {
+        let min_kx = (rect.x - self.x).div_euclid(self.size).max(0);
+        let max_kx = (rect.x + rect.w - 1 - self.x)
+            .div_euclid(self.size)
+            .min(self.width - 1);
+        // a surface at height 0 is on the bottom edge of its tile, so look one row higher
+        let min_ky = (y_min - 1 - self.y).div_euclid(self.size).max(0);
+        let max_ky = (y_max - self.y)
+            .div_euclid(self.size)
+            .min(self.data.len() as i32 / self.width - 1);
+        let mut result: Option<(i32, TileFlags)> = None;
+        for ky in min_ky..=max_ky {
+            for kx in min_kx..=max_kx {
+                let f = self.data[(ky * self.width + kx) as usize];
+                if let Some(heights) = f.slope_heights(self.size) {
+                    let height = self.slope_height(rect, kx, heights);
+                    let surface = self.y + (ky + 1) * self.size - height;
+                    if surface >= y_min
+                        && surface <= y_max
+                        && !matches!(result, Some((s, _)) if s <= surface)
+                    {
+                        result = Some((surface, f));
+                    }
+                }
+            }
+        }
+        result
+    }
+
     fn collide_dist_up(&self, rect: &IntRect, max_dist: i32) -> i32 {
         let mut possible_dist = (rect.y - self.y).rem_euclid(self.size);
         let mut ky = (rect.y - self.y).div_euclid(self.size) - 1;
@@ -179,6 +381,12 @@ impl TileBody {
     }
 
     fn collide_dist_down(&self, rect: &IntRect, max_dist: i32) -> i32 {
+        // slopes can only be landed on from above, like platforms
+        let bottom = rect.y + rect.h;
+        let max_dist = match self.slope_surface(rect, bottom, bottom + max_dist) {
+            Some((surface, _)) => surface - bottom,
+            None => max_dist,
+        };
         let mut possible_dist = (self.y - (rect.y + rect.h)).rem_euclid(self.size);
         let mut ky = (rect.y + rect.h - self.y + possible_dist).div_euclid(self.size);
         let min_kx = (rect.x - self.x).div_euclid(self.size).max(0);
@@ -245,15 +453,31 @@ impl TileBody {
 }
 
 pub struct Actor {
-    prec_x: f32,
-    prec_y: f32,
+    pub(crate) prec_x: f32,
+    pub(crate) prec_y: f32,
     pub vx: f32,
     pub vy: f32,
     pub grounded: bool,
     pub crushed: bool,
     pub drag: f32,
+    pub gravity: f32,
+    // what the tiles the actor is standing on and overlapping do, as of the last update
+    pub ground: TileFlags,
+    pub touching: TileFlags,
+    // the liquid the actor's centre was in at the start of the last update
+    pub liquid: Option<LiquidKind>,
+    // -1 or 1 if the last update ran into a wall on that side, otherwise 0
+    pub wall: i8,
 }
 
+// drag on slippery ground, unless the actor already slides further than this
+const SLIPPERY_DRAG: f32 = 0.9;
+// how far conveyors carry actors standing on them each tick
+const CONVEYOR_SPEED: f32 = 1.0;
+const BOUNCE_SPEED: f32 = 14.0;
+const MAX_FALL_SPEED: f32 = 16.0;
+const MAX_SINK_SPEED: f32 = 4.0;
+
 impl Actor {
     pub fn new(rect: &IntRect, drag: f32) -> Self {
         Self {
@@ -264,18 +488,50 @@ impl Actor {
             grounded: false,
             crushed: false,
             drag,
+            gravity: 1.0,
+            ground: TileFlags::empty(),
+            touching: TileFlags::empty(),
+            liquid: None,
+            wall: 0,
         }
     }
 
     pub fn update(resources: &SceneResources) {
         let world = resources.world_ref.lock().unwrap();
+        let volumes = liquid_volumes(&world);
         for (_, (actor, rect)) in world.query::<(&mut Actor, &mut IntRect)>().iter() {
-            actor.vy += 1.0;
-            actor.vx *= actor.drag;
-            actor.vy = actor.vy.min(16.0);
-            let vx = actor.vx;
+            let on_ground = if actor.grounded {
+                actor.ground
+            } else {
+                TileFlags::empty()
+            };
+            let (cx, cy) = rect.centre_int();
+            actor.liquid = liquid_at(&volumes, cx, cy);
+            let (gravity, drag_x, drag_y) = match actor.liquid {
+                Some(kind) => {
+                    let m = resources.movement.liquid(kind);
+                    (m.gravity, m.drag_x, m.drag_y)
+                }
+                None => (1.0, 1.0, 1.0),
+            };
+            actor.vy += actor.gravity * gravity;
+            if on_ground.is_slippery() {
+                actor.vx *= actor.drag.max(SLIPPERY_DRAG);
+            } else {
+                actor.vx *= actor.drag;
+            }
+            actor.vx *= drag_x;
+            actor.vy *= drag_y;
+            actor.vy = actor.vy.min(if actor.liquid.is_some() {
+                MAX_SINK_SPEED
+            } else {
+                MAX_FALL_SPEED
+            });
+            // conveyors move actors along without changing their velocity
+            let vx = actor.vx + on_ground.conveyor_dir() as f32 * CONVEYOR_SPEED;
             let vy = actor.vy;
             let (cx, cy) = move_actor(actor, rect, vx, vy, &world, &resources.body_index);
+            actor.wall = if cx { vx.signum() as i8 } else { 0 };
             if cx {
                 actor.vx = 0.0;
             }
@@ -283,6 +539,11 @@ impl Actor {
                 actor.vy = 0.0;
             }
             actor.grounded = check_player_grounded(rect, &world);
+            (actor.ground, actor.touching) = tile_contacts(rect, &world);
+            if actor.grounded && actor.ground.is_bounce() {
+                actor.vy = -BOUNCE_SPEED;
+                actor.grounded = false;
+            }
         }
     }
 }
@@ -296,13 +557,14 @@ pub enum PathMotionType {
     ForwardCycle,
 }
 
+#[derive(Clone)]
 pub struct PathMotion {
     pub motion_type: PathMotionType,
     pub speed: f32,
-    prec_x: f32,
-    prec_y: f32,
-    next_node: usize,
-    offsets: Vec<Vec2>,
+    pub(crate) prec_x: f32,
+    pub(crate) prec_y: f32,
+    pub(crate) next_node: usize,
+    pub(crate) offsets: Vec<Vec2>,
 }
 
 impl PathMotion {
@@ -426,6 +688,74 @@ pub fn collide_any(world: &World, body_index: &SpatialIndex, rect: &IntRect) ->
     })
 }
 
+// how far a ray goes before it meets a solid tile, if it does within max_dist
+// (dir should be normalised so that distances are in pixels)
+pub fn raycast(
+    world: &World,
+    body_index: &SpatialIndex,
+    from: Vec2,
+    dir: Vec2,
+    max_dist: f32,
+) -> Option<f32> {
+    let bodies = body_index.entities(&rect_between(from, from + dir * max_dist));
+    bodies
+        .elements
+        .iter()
+        .filter_map(|id| {
+            world
+                .get::<&TileBody>(*id)
+                .unwrap()
+                .ray_distance(from, dir, max_dist, |f| f.is_blocker())
+        })
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+// whether any obscuring tile lies on the straight line between two points
+pub fn line_obscured(world: &World, from: Vec2, to: Vec2) -> bool {
+    let bounds = rect_between(from, to);
+    let d = from.distance(to);
+    // with nowhere to go, only the tile the line starts in matters
+    let dir = if d > 0.0 {
+        (to - from) / d
+    } else {
+        vec2(1.0, 0.0)
+    };
+    // obscurers don't have to block movement, so they aren't all in the spatial index
+    world
+        .query::<&TileBody>()
+        .iter()
+        .filter(|(_, body)| body.get_rect().intersects(&bounds))
+        .any(|(_, body)| {
+            body.ray_distance(from, dir, d, |f| f.is_obscurer())
+                .is_some()
+        })
+}
+
+// whether a rect overlaps any ladder tiles or ladder rectangles
+pub fn ladder_at(world: &World, rect: &IntRect) -> bool {
+    // ladder tiles don't block anything, so they can be on bodies that aren't in the spatial index
+    world.query::<&TileBody>().iter().any(|(_, body)| {
+        body.get_rect().intersects(rect)
+            && body
+                .flags_overlapping(rect, false)
+                .contains(TileFlags::LADDER)
+    }) || world
+        .query::<(&Ladder, &IntRect)>()
+        .iter()
+        .any(|(_, (_, r))| r.intersects(rect))
+}
+
+// whether there is ground to stand on at a point, or a slope surface up to depth pixels below it
+pub fn ground_at(world: &World, body_index: &SpatialIndex, x: i32, y: i32, depth: i32) -> bool {
+    let point = IntRect::new(x, y, 1, 1);
+    let blockers = body_index.entities(&IntRect::new(x, y, 1, depth + 1));
+    blockers.elements.iter().any(|id| {
+        let body = world.get::<&TileBody>(*id).unwrap();
+        body.collide(&point, CollisionType::Blocker)
+            || body.slope_surface(&point, y, y + depth).is_some()
+    })
+}
+
 fn move_actor(
     actor: &mut Actor,
     rect: &mut IntRect,
@@ -434,13 +764,20 @@ fn move_actor(
     world: &World,
     body_index: &SpatialIndex,
 ) -> (bool, bool) {
+    let (old_x, was_grounded, was_on_slope) = (rect.x, actor.grounded, actor.ground.is_slope());
     actor.prec_x += vx;
     let targ_x = actor.prec_x.round() as i32;
     let mut collided_x = false;
     actor.prec_y += vy;
-    let targ_y = actor.prec_y.round() as i32;
+    let mut targ_y = actor.prec_y.round() as i32;
     let mut collided_y = false;
-    let blockers = body_index.entities(&motion_rect(rect, targ_x, targ_y));
+    // how far the actor might have to move up or down to follow a slope
+    let step = (targ_x - rect.x).abs() + 1;
+    let mut area = motion_rect(rect, targ_x, targ_y);
+    area.y -= step;
+    area.h += step * 2;
+    let blockers = body_index.entities(&area);
+    let depth_before = slope_depth(world, &blockers.elements, rect);
     match targ_x.cmp(&rect.x) {
         Ordering::Less => {
             // handle moving left
@@ -474,6 +811,28 @@ fn move_actor(
         actor.prec_x = rect.x as f32;
         collided_x = true;
     }
+    // walking into a slope lifts the actor onto it, unless it's too steep or too close to the ceiling
+    let depth = slope_depth(world, &blockers.elements, rect);
+    if depth > 0 {
+        let mut d = depth;
+        for id in blockers.elements.iter() {
+            d = d.min(
+                world
+                    .get::<&TileBody>(*id)
+                    .unwrap()
+                    .collide_dist_up(rect, d),
+            );
+        }
+        if depth <= step && d == depth {
+            rect.y -= depth;
+            actor.prec_y -= depth as f32;
+            targ_y -= depth;
+        } else if depth > depth_before {
+            rect.x = old_x;
+            actor.prec_x = rect.x as f32;
+            collided_x = true;
+        }
+    }
     match targ_y.cmp(&rect.y) {
         Ordering::Less => {
             // handle moving up
@@ -506,10 +865,49 @@ fn move_actor(
     if rect.y != targ_y {
         actor.prec_y = rect.y as f32;
         collided_y = true;
+    } else if was_grounded && vy >= 0.0 {
+        // keep actors walking down a slope on the ground instead of running off into the air
+        let mut d = step;
+        for id in blockers.elements.iter() {
+            d = d.min(
+                world
+                    .get::<&TileBody>(*id)
+                    .unwrap()
+                    .collide_dist_down(rect, d),
+            );
+        }
+        let bottom = rect.y + rect.h + d;
+        let onto_slope = blockers.elements.iter().any(|id| {
+            world
+                .get::<&TileBody>(*id)
+                .unwrap()
+                .slope_surface(rect, bottom, bottom)
+                .is_some()
+        });
+        if d > 0 && d < step && (onto_slope || was_on_slope) {
+            rect.y += d;
+            actor.prec_y = rect.y as f32;
+            collided_y = true;
+        }
     }
     (collided_x, collided_y)
 }
 
+// how far the bottom of a rect is below the surface of any slope it's overlapping
+fn slope_depth(world: &World, ids: &[Entity], rect: &IntRect) -> i32 {
+    let bottom = rect.y + rect.h;
+    ids.iter()
+        .filter_map(|id| {
+            world
+                .get::<&TileBody>(*id)
+                .unwrap()
+                .slope_surface(rect, rect.y, bottom - 1)
+        })
+        .map(|(surface, _)| bottom - surface)
+        .max()
+        .unwrap_or(0)
+}
+
 fn move_body(
     world: &World,
     spatial_index: &mut SpatialIndex,
@@ -619,10 +1017,34 @@ fn move_body(
 }
 
 fn check_player_grounded(player_rect: &IntRect, world: &World) -> bool {
+    let bottom = player_rect.y + player_rect.h;
     world.query::<&TileBody>().iter().any(|(_, c)| {
         c.collide(
             &feet_rect(player_rect),
             CollisionType::TopOfBlockerOrPlatform,
-        )
+        ) || c.slope_surface(player_rect, bottom, bottom).is_some()
     })
 }
+
+// the tiles under an actor's feet and the tiles overlapping it, on every body so that
+// moving doors and lifts behave the same as the terrain
+fn tile_contacts(rect: &IntRect, world: &World) -> (TileFlags, TileFlags) {
+    let feet = feet_rect(rect);
+    let around = IntRect::new(rect.x - 1, rect.y - 1, rect.w + 2, rect.h + 2);
+    let mut ground = TileFlags::empty();
+    let mut touching = TileFlags::empty();
+    // the special tiles don't have to be solid, so not every body is in the spatial index,
+    // but most of them can be skipped without looking at their tiles
+    for (_, body) in world
+        .query::<&TileBody>()
+        .iter()
+        .filter(|(_, body)| body.get_rect().intersects(&around))
+    {
+        ground |= body.flags_overlapping(&feet, true);
+        if let Some((_, flags)) = body.slope_surface(rect, feet.y, feet.y) {
+            ground |= flags;
+        }
+        touching |= body.flags_overlapping(rect, false);
+    }
+    (ground, touching)
+}
@@ -8,11 +8,12 @@ use std::collections::HashMap;
 pub enum PickupType {
     Heart,
     Ammo(AmmoType, AmmoQuantity),
+    WallJump,
 }
 
 pub struct Pickup {
     pub typ: PickupType,
-    touched: bool,
+    pub(crate) touched: bool,
 }
 
 pub fn add_heart(world: &mut World, x: i32, y: i32) {
@@ -45,6 +46,21 @@ pub fn add_ammo(world: &mut World, x: i32, y: i32, typ: AmmoType, amt: AmmoQuant
     ));
 }
 
+pub fn add_wall_jump(world: &mut World, x: i32, y: i32) {
+    let rect = IntRect::new(x - 8, y - 16, 16, 16);
+    let draw = crate::draw::PickupSprite::new();
+    let actor = Actor::new(&rect, 0.4);
+    world.spawn((
+        rect,
+        draw,
+        actor,
+        Pickup {
+            typ: PickupType::WallJump,
+            touched: false,
+        },
+    ));
+}
+
 impl Pickup {
     pub fn update(resources: &mut SceneResources, buffer: &mut CommandBuffer) -> Option<()> {
         let world = resources.world_ref.lock().unwrap();
@@ -81,6 +97,16 @@ impl Pickup {
                                 .add(format!("Picked up {}.", ammo_name(typ, amt)));
                         }
                     }
+                    PickupType::WallJump => {
+                        buffer.despawn(id);
+                        c.wall_jump = true;
+                        if let Some(snapshot) = resources.checkpoint.as_mut() {
+                            snapshot.unlock_wall_jump();
+                        }
+                        resources
+                            .messages
+                            .add("You can now jump off walls.".to_owned());
+                    }
                 }
             }
         }
@@ -89,7 +115,7 @@ impl Pickup {
 }
 
 pub struct WeaponPickup {
-    touched: bool,
+    pub(crate) touched: bool,
     pub typ: WeaponType,
 }
 
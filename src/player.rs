@@ -1,29 +1,38 @@
 use crate::draw::PlayerSprite;
 use crate::input::{Input, KeyState, VirtualKey};
-use crate::physics::{Actor, IntRect, Secrecy, TriggerZone};
+use crate::physics::{ladder_at, Actor, IntRect, Secrecy, TriggerZone};
 use crate::pickup::WeaponPickup;
 use crate::resources::SceneResources;
 use crate::switch::Switch;
 use crate::vfx::create_explosion;
-use crate::weapon::{new_weapon, select_fireable_weapon, weapon_name_indef, WeaponType};
+use crate::weapon::{new_weapon, select_fireable_weapon, weapon_name_indef, Aim, WeaponType};
 use hecs::{CommandBuffer, Entity};
 use std::collections::{HashMap, HashSet};
 
 pub struct Controller {
-    jump_frames: u32,
-    zones: HashSet<String>,
+    pub(crate) jump_frames: u32,
+    // frames since the player was last on the ground and since jump was last pressed
+    pub(crate) air_frames: u32,
+    pub(crate) jump_press_frames: u32,
+    pub(crate) climbing: bool,
+    pub wall_jump: bool,
+    pub(crate) zones: HashSet<String>,
     pub touched_weapons: HashMap<WeaponType, Entity>,
-    facing: i8,
-    fire_timer: u32,
-    hurt_timer: u8,
+    pub(crate) facing: i8,
+    pub(crate) fire_timer: u32,
+    pub(crate) hurt_timer: u8,
     pub hp: u8,
-    god_mode: bool,
+    pub(crate) god_mode: bool,
 }
 
 impl Controller {
     pub fn new() -> Self {
         Self {
             jump_frames: 0,
+            air_frames: u32::MAX,
+            jump_press_frames: u32::MAX,
+            climbing: false,
+            wall_jump: false,
             zones: HashSet::new(),
             touched_weapons: HashMap::new(),
             facing: 1,
@@ -36,6 +45,7 @@ impl Controller {
 
     pub fn update(resources: &mut SceneResources, buffer: &mut CommandBuffer, input: &Input) {
         let world = resources.world_ref.lock().unwrap();
+        let cfg = &resources.movement;
         let mut q = world.query::<(&mut Actor, &IntRect, &mut PlayerSprite, &mut Controller)>();
         for (id, (player, p_rect, sprite, controller)) in q.iter() {
             let mut new_zones: HashSet<String> = HashSet::new();
@@ -60,13 +70,18 @@ impl Controller {
                 }
             }
             controller.zones = new_zones;
+            let accel = if player.grounded && player.ground.is_slippery() {
+                cfg.slippery_accel
+            } else {
+                cfg.accel
+            };
             if input.is_down(VirtualKey::Left) {
-                player.vx -= 3.0;
+                player.vx -= accel;
                 controller.facing = -1;
                 sprite.flipped = false;
             }
             if input.is_down(VirtualKey::Right) {
-                player.vx += 3.0;
+                player.vx += accel;
                 controller.facing = 1;
                 sprite.flipped = true;
             }
@@ -102,6 +117,9 @@ impl Controller {
                             {
                                 resources.weapons.remove(n);
                             }
+                            if let Some(w) = resources.weapons.front_mut() {
+                                w.deselect();
+                            }
                             // now we can't have the backup laser so we can just use the len()
                             // to work out if the inventory is full
                             if resources.weapons.len() < 3 {
@@ -125,23 +143,97 @@ impl Controller {
                     }
                 }
             }
-            if player.grounded && input.is_pressed(VirtualKey::Jump) {
-                player.vy = -6.0;
-                controller.jump_frames = 5;
+            // ladders are climbed from the middle of the player, including the pixel under
+            // their feet so that they can stand at the top and climb back down from there
+            let ladder_rect =
+                |dy: i32| IntRect::new(p_rect.x + p_rect.w / 2 - 1, p_rect.y + dy, 2, p_rect.h + 1);
+            if !ladder_at(&world, &ladder_rect(0)) {
+                controller.climbing = false;
+            } else if !input.is_down(VirtualKey::Fire)
+                && (input.is_down(VirtualKey::Up) || input.is_down(VirtualKey::Down))
+            {
+                // with fire held, up and down are for aiming instead
+                controller.climbing = true;
+            }
+            controller.wall_jump |= resources.script_engine.wall_jump_flag();
+            // sliding down a wall needs the player to keep pushing against it
+            let sliding = controller.wall_jump
+                && !player.grounded
+                && !controller.climbing
+                && (player.wall < 0 && input.is_down(VirtualKey::Left)
+                    || player.wall > 0 && input.is_down(VirtualKey::Right));
+            controller.air_frames = if player.grounded {
+                0
+            } else {
+                controller.air_frames.saturating_add(1)
+            };
+            controller.jump_press_frames = if input.is_pressed(VirtualKey::Jump) {
+                0
+            } else {
+                controller.jump_press_frames.saturating_add(1)
+            };
+            // jumps still work just after running off a ledge or just before landing
+            if (controller.air_frames <= cfg.coyote_frames || controller.climbing)
+                && controller.jump_press_frames <= cfg.jump_buffer_frames
+            {
+                player.vy = -cfg.jump_speed;
+                // holding jump doesn't make it any higher in liquid
+                controller.jump_frames = if player.liquid.is_some() {
+                    0
+                } else {
+                    cfg.jump_hold_frames
+                };
+                controller.climbing = false;
+                controller.air_frames = u32::MAX;
+                controller.jump_press_frames = u32::MAX;
+            } else if sliding && input.is_pressed(VirtualKey::Jump) {
+                // kick off away from the wall
+                player.vy = -cfg.jump_speed;
+                player.vx = -player.wall as f32 * cfg.wall_jump_speed;
+                controller.jump_frames = cfg.jump_hold_frames;
+                controller.facing = -player.wall;
+                sprite.flipped = player.wall < 0;
+            } else if let Some(kind) = player.liquid.filter(|_| input.is_pressed(VirtualKey::Jump))
+            {
+                player.vy = -cfg.liquid(kind).swim_speed;
+                controller.jump_frames = 0;
             } else if controller.jump_frames > 0
                 && input.is_down(VirtualKey::Jump)
                 && player.vy < 0.0
             {
-                player.vy = -10.0;
+                player.vy = -cfg.jump_hold_speed;
                 controller.jump_frames -= 1;
             } else {
                 controller.jump_frames = 0;
             }
+            if controller.climbing {
+                let speed = if input.is_down(VirtualKey::Up) {
+                    // stop at the top of the ladder instead of climbing off the end
+                    -(1..=cfg.climb_speed)
+                        .rev()
+                        .find(|&d| ladder_at(&world, &ladder_rect(-d)))
+                        .unwrap_or(0)
+                } else if input.is_down(VirtualKey::Down) {
+                    cfg.climb_speed
+                } else {
+                    0
+                };
+                player.vy = speed as f32;
+                player.gravity = 0.0;
+                sprite.n += speed.abs();
+            } else if sliding && player.vy >= 0.0 {
+                player.vy = player.vy.min(cfg.wall_slide_speed);
+                player.gravity = cfg.wall_slide_gravity;
+            } else {
+                player.gravity = 1.0;
+            }
+            sprite.climbing = controller.climbing;
             if player.grounded {
                 sprite.n += player.vx.abs() as i32;
             }
             if input.is_pressed(VirtualKey::PrevWeapon) {
                 if resources.weapons.len() > 1 {
+                    resources.weapons[0].deselect();
                     resources.weapons.rotate_left(1);
                     resources.selector.change(-1.0);
                 } else {
@@ -150,22 +242,37 @@ impl Controller {
             }
             if input.is_pressed(VirtualKey::NextWeapon) {
                 if resources.weapons.len() > 1 {
+                    resources.weapons[0].deselect();
                     resources.weapons.rotate_right(1);
                     resources.selector.change(1.0);
                 } else {
                     resources.selector.change(-0.1);
                 }
             }
+            let aim = controller.aim(input, player.grounded);
+            sprite.aim = aim;
             let fks = input.state(VirtualKey::Fire);
             let w = &mut resources.weapons[0];
             let t = w.get_ammo_type();
             let n = w.get_ammo_use();
             if resources.ammo[t] >= n {
                 // can fire current weapon, up to the weapon to say if we should
-                if w.update(buffer, player, p_rect, controller.facing, fks) {
+                if w.update(
+                    buffer,
+                    &mut resources.rng,
+                    &mut resources.cosmetic_rng,
+                    player,
+                    p_rect,
+                    aim,
+                    fks,
+                ) {
                     controller.fire_timer = 0;
                     sprite.firing = true;
                     resources.ammo[t] -= n;
+                    if controller.climbing {
+                        // hold on to the ladder instead of being knocked off by the recoil
+                        player.vx = 0.0;
+                    }
                 }
             } else {
                 // can't fire current weapon, try changing if player pressed fire
@@ -182,6 +289,10 @@ impl Controller {
             if controller.fire_timer > 5 {
                 sprite.firing = false;
             }
+            let hazard = player.ground.is_hazard() || player.touching.is_hazard();
+            if hazard || matches!(player.liquid, Some(l) if l.damage() > 0) {
+                controller.hurt();
+            }
             if controller.hurt_timer > 0 {
                 controller.hurt_timer -= 1;
                 sprite.blink = (controller.hurt_timer / 3) % 2 == 0;
@@ -192,17 +303,44 @@ impl Controller {
                 buffer.remove_one::<PlayerSprite>(id);
                 buffer.remove_one::<Controller>(id);
                 let (px, py) = p_rect.centre_int();
-                create_explosion(buffer, px, py);
+                create_explosion(buffer, &mut resources.cosmetic_rng, px, py);
                 resources.messages.add("You have died.".to_owned());
             }
-            #[cfg(debug_assertions)]
-            {
-                use macroquad::prelude::{is_key_down, KeyCode};
-                if is_key_down(KeyCode::Q) && is_key_down(KeyCode::D) && !controller.god_mode {
-                    controller.god_mode = true;
-                    resources.messages.add("God mode enabled!".to_owned());
-                }
-            }
+        }
+    }
+
+    // up and down aim (diagonally while moving, and always on the ground since there's no
+    // point shooting the floor) except once on a ladder where they're needed for climbing
+    fn aim(&self, input: &Input, grounded: bool) -> Aim {
+        let moving = input.is_down(VirtualKey::Left) || input.is_down(VirtualKey::Right);
+        let dy = if self.climbing {
+            0
+        } else if input.is_down(VirtualKey::Up) {
+            -1
+        } else if input.is_down(VirtualKey::Down) {
+            1
+        } else {
+            0
+        };
+        let dx = if dy == 0 || moving || (dy > 0 && grounded) {
+            self.facing
+        } else {
+            0
+        };
+        Aim {
+            facing: self.facing,
+            dx,
+            dy,
+        }
+    }
+
+    // a controller with full health that keeps what was unlocked and which zones the player is in
+    pub fn respawn(&self) -> Self {
+        Self {
+            wall_jump: self.wall_jump,
+            zones: self.zones.clone(),
+            facing: self.facing,
+            ..Self::new()
         }
     }
 
@@ -211,12 +349,24 @@ impl Controller {
     }
 
     pub fn hurt(&mut self) {
-        if self.hurt_timer == 0 && self.hp > 0 && !self.god_mode {
-            self.hp -= 1;
+        self.hurt_by(1);
+    }
+
+    // for things that can take more than one hp at once, like blasts
+    pub fn hurt_by(&mut self, damage: u8) {
+        if self.hurt_timer == 0 && self.hp > 0 && !self.god_mode && damage > 0 {
+            self.hp = self.hp.saturating_sub(damage);
             self.hurt_timer = 24;
         }
     }
 
+    // for the debug cheat, which only says so the first time
+    pub fn enable_god_mode(&mut self) -> bool {
+        let was_enabled = self.god_mode;
+        self.god_mode = true;
+        !was_enabled
+    }
+
     pub fn was_hurt(&self) -> bool {
         self.hurt_timer >= 23
     }
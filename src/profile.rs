@@ -52,6 +52,7 @@ pub struct Profiler {
     times: EnumMap<Phase, VecDeque<f64>>,
     start: f64,
     phase: Option<Phase>,
+    enabled: bool,
 }
 
 #[cfg(debug_assertions)]
@@ -61,9 +62,20 @@ impl Profiler {
             times: EnumMap::default(),
             start: 0.0,
             phase: None,
+            enabled: true,
+        }
+    }
+    // timing needs a window, so headless simulation uses one of these instead
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::new()
         }
     }
     pub fn start(&mut self, new_phase: Phase) {
+        if !self.enabled {
+            return;
+        }
         self.stop();
         self.phase = Some(new_phase);
         self.start = get_time();
@@ -129,6 +141,9 @@ impl Profiler {
     pub fn new() -> Self {
         Self {}
     }
+    pub fn disabled() -> Self {
+        Self {}
+    }
     pub fn start(&mut self, _phase: Phase) {}
     pub fn stop(&mut self) {}
 }
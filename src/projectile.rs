@@ -1,24 +1,44 @@
 use crate::draw::ZapSprite;
 use crate::enemy::EnemyHittable;
+use crate::explosion::{explode, Blast};
 use crate::physics::collide_any;
 use crate::physics::IntRect;
 use crate::player::Controller;
 use crate::resources::SceneResources;
+use crate::rng::Rng;
 use crate::vfx::Explosion;
 use crate::vfx::FireballEffect;
+use crate::vfx::SmokeParticle;
 use crate::vfx::ZapFlash;
-use hecs::{CommandBuffer, World};
+use hecs::{CommandBuffer, Entity, World};
+use macroquad::math::{vec2, Vec2};
 
 pub struct DamageEnemies {}
 pub struct DamagePlayer {}
 pub struct LaserImpact {}
 pub struct FireballSplit {}
 pub struct ProjectileGravity {}
-pub struct ProjectileDrag {}
+// shots fired straight ahead stop once they slow down horizontally, aimed ones once their whole
+// speed has dropped off
+pub struct ProjectileDrag {
+    pub(crate) aimed: bool,
+}
+pub struct Rocket {}
+
+// keeps going through enemies, hurting each one once, until it has hit enough of them
+pub struct Piercing {
+    pub(crate) damage: u16,
+    pub(crate) max_hits: usize,
+    pub(crate) hit: Vec<Entity>,
+}
+
+fn rocket_blast(x: i32, y: i32) -> Blast {
+    Blast::new(x, y, 40.0, 6, 12.0)
+}
 
 pub struct Projectile {
-    prec_x: f32,
-    prec_y: f32,
+    pub(crate) prec_x: f32,
+    pub(crate) prec_y: f32,
     pub vx: f32,
     pub vy: f32,
 }
@@ -51,17 +71,43 @@ impl Projectile {
                 }
                 if world.satisfies::<&FireballSplit>(e).unwrap_or(false) {
                     let (x, y) = find_collision_pos(&world, resources, ox, oy, rect);
-                    spawn_mini_fireballs(buffer, x + 8, y + 8);
+                    spawn_mini_fireballs(buffer, &mut resources.rng, x + 8, y + 8);
+                }
+                if world.satisfies::<&Rocket>(e).unwrap_or(false) {
+                    let (x, y) = find_collision_pos(&world, resources, ox, oy, rect);
+                    explode(
+                        buffer,
+                        &mut resources.cosmetic_rng,
+                        rocket_blast(x + rect.w / 2, y + rect.h / 2),
+                    );
                 }
             }
         }
-        for (e, (proj, rect, _)) in world
-            .query::<(&mut Projectile, &mut IntRect, &DamageEnemies)>()
+        for (e, (proj, rect, _, mut piercing)) in world
+            .query::<(
+                &mut Projectile,
+                &mut IntRect,
+                &DamageEnemies,
+                Option<&mut Piercing>,
+            )>()
             .iter()
         {
             let mut live = true;
-            for (_, (en, e_rect)) in world.query::<(&mut EnemyHittable, &IntRect)>().iter() {
+            for (en_id, (en, e_rect)) in world.query::<(&mut EnemyHittable, &IntRect)>().iter() {
                 if live && en.hp > 0 && rect.intersects(e_rect) {
+                    if let Some(p) = &mut piercing {
+                        if !p.hit.contains(&en_id) {
+                            p.hit.push(en_id);
+                            en.hurt(p.damage);
+                            let (cx, cy) = e_rect.centre_int();
+                            buffer.spawn((ZapFlash::new_from_centre(cx, cy),));
+                            if p.hit.len() >= p.max_hits {
+                                buffer.despawn(e);
+                                live = false;
+                            }
+                        }
+                        continue;
+                    }
                     buffer.despawn(e);
                     if world.satisfies::<&LaserImpact>(e).unwrap_or(false) {
                         let sx = if proj.vx > 0.0 {
@@ -72,9 +118,18 @@ impl Projectile {
                         buffer.spawn((ZapFlash::new_from_centre(sx, rect.y + 2),));
                     }
                     if world.satisfies::<&FireballSplit>(e).unwrap_or(false) {
-                        spawn_mini_fireballs(buffer, rect.x + 8, rect.y + 8);
+                        spawn_mini_fireballs(buffer, &mut resources.rng, rect.x + 8, rect.y + 8);
+                    }
+                    if world.satisfies::<&Rocket>(e).unwrap_or(false) {
+                        // the blast does the damage
+                        explode(
+                            buffer,
+                            &mut resources.cosmetic_rng,
+                            rocket_blast(rect.x + rect.w / 2, rect.y + rect.h / 2),
+                        );
+                    } else {
+                        en.hurt(1);
                     }
-                    en.hurt(1);
                     live = false;
                 }
             }
@@ -99,7 +154,12 @@ impl Projectile {
                                 buffer.spawn((ZapFlash::new_from_centre(sx, rect.y + 2),));
                             }
                             if world.satisfies::<&FireballSplit>(id).unwrap_or(false) {
-                                spawn_mini_fireballs(buffer, rect.x + 8, rect.y + 8);
+                                spawn_mini_fireballs(
+                                    buffer,
+                                    &mut resources.rng,
+                                    rect.x + 8,
+                                    rect.y + 8,
+                                );
                             }
                         }
                     }
@@ -113,19 +173,36 @@ impl Projectile {
         {
             proj.vy += 0.2;
         }
-        for (id, (proj, _)) in world.query::<(&mut Projectile, &ProjectileDrag)>().iter() {
+        for (_, (proj, rect, _)) in world.query::<(&Projectile, &IntRect, &Rocket)>().iter() {
+            let a = std::f32::consts::PI / -2.0 + resources.cosmetic_rng.gen_range(-0.3, 0.3);
+            buffer.spawn((SmokeParticle::new_from_centre(
+                &mut resources.cosmetic_rng,
+                rect.x + rect.w / 2 - proj.vx.signum() as i32 * 4,
+                rect.y + rect.h / 2 - proj.vy.signum() as i32 * 4,
+                a,
+                4.0,
+            ),));
+        }
+        for (id, (proj, drag)) in world.query::<(&mut Projectile, &ProjectileDrag)>().iter() {
             proj.vx *= 0.8;
             proj.vy *= 0.8;
-            if proj.vx.abs() < 2.0 {
+            let speed = if drag.aimed {
+                vec2(proj.vx, proj.vy).length()
+            } else {
+                proj.vx.abs()
+            };
+            if speed < 2.0 {
                 buffer.despawn(id);
             }
         }
     }
 }
 
-fn spawn_mini_fireballs(buffer: &mut CommandBuffer, x: i32, y: i32) {
+fn spawn_mini_fireballs(buffer: &mut CommandBuffer, rng: &mut Rng, x: i32, y: i32) {
     buffer.spawn((Explosion::new_from_centre(x, y),));
-    let mut a = quad_rand::gen_range(0.0, std::f32::consts::TAU);
+    // the burst only pushes everyone around, it's the mini fireballs that do the damage
+    buffer.spawn((Blast::new(x, y, 24.0, 0, 6.0),));
+    let mut a = rng.gen_range(0.0, std::f32::consts::TAU);
     a += std::f32::consts::TAU / std::f32::consts::E;
     let rect = IntRect::new(x - 4, y - 4, 8, 8);
     for _ in 0..6 {
@@ -161,17 +238,48 @@ fn find_collision_pos(
     (r.x, r.y)
 }
 
-pub fn make_player_projectile(buffer: &mut CommandBuffer, rect: IntRect, vx: f32) {
-    let proj = Projectile::new(&rect, vx, 0.0);
+pub fn make_player_projectile(buffer: &mut CommandBuffer, rect: IntRect, v: Vec2) {
+    let proj = Projectile::new(&rect, v.x, v.y);
+    // horizontal shots use the sprite as it is, whichever way they go
+    let angle = if v.y == 0.0 { 0.0 } else { v.y.atan2(v.x) };
     buffer.spawn((
         rect,
-        ZapSprite::new(),
+        ZapSprite::aimed(angle),
         proj,
         DamageEnemies {},
         LaserImpact {},
     ));
 }
 
+// power is how many enemies it goes through and how much it hurts each of them
+pub fn make_charged_projectile(buffer: &mut CommandBuffer, rect: IntRect, v: Vec2, power: u8) {
+    let proj = Projectile::new(&rect, v.x, v.y);
+    let angle = if v.y == 0.0 { 0.0 } else { v.y.atan2(v.x) };
+    buffer.spawn((
+        rect,
+        ZapSprite::charged(angle),
+        proj,
+        DamageEnemies {},
+        LaserImpact {},
+        Piercing {
+            damage: power as u16,
+            max_hits: power as usize,
+            hit: Vec::new(),
+        },
+    ));
+}
+
+pub fn make_rocket(buffer: &mut CommandBuffer, rect: IntRect, v: Vec2) {
+    let proj = Projectile::new(&rect, v.x, v.y);
+    buffer.spawn((
+        rect,
+        FireballEffect::new(4.0),
+        proj,
+        DamageEnemies {},
+        Rocket {},
+    ));
+}
+
 pub fn make_enemy_laser(buffer: &mut CommandBuffer, rect: IntRect, vx: f32) {
     let proj = Projectile::new(&rect, vx, 0.0);
     buffer.spawn((
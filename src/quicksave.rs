@@ -0,0 +1,802 @@
+use crate::beam::Beam;
+use crate::camera::reset_camera;
+use crate::checkpoint::{restore_checkpoint, Checkpoint};
+use crate::draw::{PlayerSprite, ZapSprite};
+use crate::enemy::{DogBehaviour, EnemyHittable, ParrotBehaviour, ParrotState};
+use crate::explosion::Blast;
+use crate::liquid::LiquidKind;
+use crate::loader::TileFlags;
+use crate::physics::{Actor, IntRect, PathMotion, PathMotionType, Secrecy, TileBody, TriggerZone};
+use crate::pickup::{Pickup, WeaponPickup};
+use crate::player::Controller;
+use crate::projectile::{
+    DamageEnemies, DamagePlayer, FireballSplit, LaserImpact, Piercing, Projectile, ProjectileDrag,
+    ProjectileGravity, Rocket,
+};
+use crate::resources::{Inventory, SceneResources};
+use crate::rng::Rng;
+use crate::save::Storage;
+use crate::switch::Switch;
+use crate::vfx::{BeamEffect, Explosion, FireParticle, FireballEffect, SmokeParticle, ZapFlash};
+use crate::weapon::{new_weapon, WeaponType};
+use enum_iterator::all;
+use hecs::{Component, Entity, EntityBuilder, EntityRef, RefMut, World};
+use macroquad::math::vec2;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::str::{FromStr, SplitWhitespace};
+
+pub const QUICKSAVE_FILE: &str = "quicksave.txt";
+
+// a moment partway through a level, stored as text:
+//   level <path>
+//   seed <n>
+//   weapons <index>... / ammo <n>... (what the player started the level with)
+//   carrying weapons <index>... / carrying ammo <n>... (and what they have now)
+//   stats <frames> <kills> <items> <secrets>
+//   rng <state>
+//   selector <timer> <offset> <hidden>
+//   var / flags / after / curry (the script's variables and what it's waiting to do)
+//   entity <id> (for everything from the map in the order the world stores it, followed by a
+//     line for each part of it that can change: rect, actor, body, path, enemy, dog, parrot,
+//     pickup, weapon, switch, zone, checkpoint, player and sprite)
+//   shot (for each shot, blast and beam in flight, followed by projectile, piercing, zap,
+//     fireball, blast or beam lines for what it's made of)
+// entities are matched up by id with a fresh load of the level, and any that aren't in the
+// quicksave have been killed or collected since. effects like smoke and flashes are left out.
+// weapons aren't saved beyond their types, so the ones carried come back as they were when new,
+// with no charge built up and no fire delay left.
+pub struct Quicksave {
+    pub level: String,
+    pub seed: u64,
+    pub inventory: Inventory,
+    // everything after the level is loaded, with line numbers for errors
+    lines: Vec<(usize, String)>,
+}
+
+// the values on one line of a quicksave, read in order
+pub struct Fields<'a> {
+    parts: SplitWhitespace<'a>,
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Self {
+            parts: line.split_whitespace(),
+        }
+    }
+
+    pub fn word(&mut self) -> Result<&'a str, String> {
+        self.parts.next().ok_or_else(|| "missing value".to_owned())
+    }
+
+    pub fn value<T: FromStr>(&mut self) -> Result<T, String> {
+        let word = self.word()?;
+        word.parse().map_err(|_| format!("bad value {}", word))
+    }
+
+    // written by opt, with - for nothing
+    pub fn value_opt<T: FromStr>(&mut self) -> Result<Option<T>, String> {
+        match self.parts.clone().next() {
+            Some("-") => {
+                self.parts.next();
+                Ok(None)
+            }
+            _ => self.value().map(Some),
+        }
+    }
+
+    // by position in WeaponType, as in inventories
+    pub fn weapon(&mut self) -> Result<WeaponType, String> {
+        let n: usize = self.value()?;
+        all::<WeaponType>()
+            .nth(n)
+            .ok_or_else(|| format!("unknown weapon {}", n))
+    }
+
+    // any entity, including ones that have gone since
+    pub fn id(&mut self) -> Result<Entity, String> {
+        let bits: u64 = self.value()?;
+        Entity::from_bits(bits).ok_or_else(|| format!("bad entity {}", bits))
+    }
+
+    // only entities that the freshly loaded level has too
+    pub fn entity(&mut self, world: &World) -> Result<Entity, String> {
+        let id = self.id()?;
+        if !world.contains(id) {
+            return Err(format!("entity {} isn't in this level", id.to_bits()));
+        }
+        Ok(id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.clone().next().is_none()
+    }
+
+    pub fn rest(self) -> Vec<&'a str> {
+        self.parts.collect()
+    }
+
+    pub fn end(mut self) -> Result<(), String> {
+        match self.parts.next() {
+            None => Ok(()),
+            Some(word) => Err(format!("unexpected value {}", word)),
+        }
+    }
+}
+
+fn opt<T: Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_owned(), |v| v.to_string())
+}
+
+fn weapon_field(typ: WeaponType) -> usize {
+    all::<WeaponType>().position(|t| t == typ).unwrap()
+}
+
+// a name and a node, which is 0 for types that don't go to one
+pub(crate) fn motion_fields(motion: &PathMotionType) -> String {
+    let (name, node) = match motion {
+        PathMotionType::Static => ("static", 0),
+        PathMotionType::GoToNodeForward(n) => ("forward_to", *n),
+        PathMotionType::GoToNodeBackward(n) => ("backward_to", *n),
+        PathMotionType::ForwardOnce => ("forward_once", 0),
+        PathMotionType::ForwardCycle => ("forward_cycle", 0),
+    };
+    format!("{} {}", name, node)
+}
+
+pub(crate) fn parse_motion(f: &mut Fields) -> Result<PathMotionType, String> {
+    let name = f.word()?;
+    let node = f.value()?;
+    match name {
+        "static" => Ok(PathMotionType::Static),
+        "forward_to" => Ok(PathMotionType::GoToNodeForward(node)),
+        "backward_to" => Ok(PathMotionType::GoToNodeBackward(node)),
+        "forward_once" => Ok(PathMotionType::ForwardOnce),
+        "forward_cycle" => Ok(PathMotionType::ForwardCycle),
+        _ => Err(format!("unknown motion {}", name)),
+    }
+}
+
+fn secrecy_name(secrecy: &Secrecy) -> &'static str {
+    match secrecy {
+        Secrecy::NotSecret => "none",
+        Secrecy::Hidden => "hidden",
+        Secrecy::Found => "found",
+    }
+}
+
+const PARROT_STATES: [(ParrotState, &str); 4] = [
+    (ParrotState::Wait, "wait"),
+    (ParrotState::Move, "move"),
+    (ParrotState::Attack, "attack"),
+    (ParrotState::Fall, "fall"),
+];
+
+fn is_shot(e: &EntityRef) -> bool {
+    e.has::<Projectile>() || e.has::<Blast>() || e.has::<Beam>()
+}
+
+// only there to be looked at, so they don't need saving
+fn is_effect(e: &EntityRef) -> bool {
+    e.has::<ZapFlash>()
+        || e.has::<BeamEffect>()
+        || e.has::<Explosion>()
+        || e.has::<FireParticle>()
+        || e.has::<SmokeParticle>()
+}
+
+// what kind of shot it is, from the parts that don't hold anything
+fn shot_markers(e: &EntityRef) -> Vec<&'static str> {
+    let drag = e.get::<&ProjectileDrag>().map(|d| d.aimed);
+    let markers = [
+        ("damage_enemies", e.has::<DamageEnemies>()),
+        ("damage_player", e.has::<DamagePlayer>()),
+        ("laser_impact", e.has::<LaserImpact>()),
+        ("fireball_split", e.has::<FireballSplit>()),
+        ("gravity", e.has::<ProjectileGravity>()),
+        ("drag", drag == Some(false)),
+        ("aimed_drag", drag == Some(true)),
+        ("rocket", e.has::<Rocket>()),
+    ];
+    markers
+        .iter()
+        .filter(|(_, has)| *has)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+fn add_shot_marker(shot: &mut EntityBuilder, name: &str) -> Result<(), String> {
+    match name {
+        "damage_enemies" => shot.add(DamageEnemies {}),
+        "damage_player" => shot.add(DamagePlayer {}),
+        "laser_impact" => shot.add(LaserImpact {}),
+        "fireball_split" => shot.add(FireballSplit {}),
+        "gravity" => shot.add(ProjectileGravity {}),
+        "drag" => shot.add(ProjectileDrag { aimed: false }),
+        "aimed_drag" => shot.add(ProjectileDrag { aimed: true }),
+        "rocket" => shot.add(Rocket {}),
+        other => return Err(format!("unknown shot part {}", other)),
+    };
+    Ok(())
+}
+
+fn entity_lines(e: &EntityRef, lines: &mut Vec<String>) {
+    lines.push(format!("entity {}", e.entity().to_bits()));
+    if let Some(r) = e.get::<&IntRect>() {
+        lines.push(format!("rect {} {} {} {}", r.x, r.y, r.w, r.h));
+    }
+    if let Some(a) = e.get::<&Actor>() {
+        lines.push(format!(
+            "actor {} {} {} {} {} {} {} {} {} {} {} {}",
+            a.prec_x,
+            a.prec_y,
+            a.vx,
+            a.vy,
+            a.grounded,
+            a.crushed,
+            a.drag,
+            a.gravity,
+            a.ground.bits(),
+            a.touching.bits(),
+            opt(a.liquid.map(|l| l.name())),
+            a.wall
+        ));
+    }
+    if let Some(body) = e.get::<&TileBody>() {
+        lines.push(format!("body {} {}", body.x, body.y));
+    }
+    if let Some(pm) = e.get::<&PathMotion>() {
+        let mut line = format!(
+            "path {} {} {} {} {}",
+            motion_fields(&pm.motion_type),
+            pm.speed,
+            pm.prec_x,
+            pm.prec_y,
+            pm.next_node
+        );
+        for v in &pm.offsets {
+            line.push_str(&format!(" {} {}", v.x, v.y));
+        }
+        lines.push(line);
+    }
+    if let Some(h) = e.get::<&EnemyHittable>() {
+        lines.push(format!("enemy {} {}", h.hp, h.was_hit));
+    }
+    if let Some(dog) = e.get::<&DogBehaviour>() {
+        lines.push(format!("dog {} {}", dog.dir, opt(dog.jump_y)));
+    }
+    if let Some(p) = e.get::<&ParrotBehaviour>() {
+        let (_, state) = PARROT_STATES.iter().find(|(s, _)| *s == p.state).unwrap();
+        lines.push(format!(
+            "parrot {} {} {} {}",
+            state, p.state_timer, p.attack_timer, p.facing
+        ));
+    }
+    if let Some(p) = e.get::<&Pickup>() {
+        lines.push(format!("pickup {}", p.touched));
+    }
+    // the type can change when the player swaps a weapon for it
+    if let Some(wp) = e.get::<&WeaponPickup>() {
+        lines.push(format!("weapon {} {}", weapon_field(wp.typ), wp.touched));
+    }
+    if let Some(s) = e.get::<&Switch>() {
+        lines.push(format!("switch {}", s.enabled));
+    }
+    if let Some(tz) = e.get::<&TriggerZone>() {
+        lines.push(format!("zone {}", secrecy_name(&tz.secrecy)));
+    }
+    if let Some(cp) = e.get::<&Checkpoint>() {
+        lines.push(format!("checkpoint {}", cp.active));
+    }
+    // zones and touched weapons are written as a count followed by that many of them
+    if let Some(c) = e.get::<&Controller>() {
+        let mut zones: Vec<&String> = c.zones.iter().collect();
+        zones.sort();
+        let mut line = format!(
+            "player {} {} {} {} {} {} {} {} {} {} {}",
+            c.jump_frames,
+            c.air_frames,
+            c.jump_press_frames,
+            c.climbing,
+            c.wall_jump,
+            c.facing,
+            c.fire_timer,
+            c.hurt_timer,
+            c.hp,
+            c.god_mode,
+            zones.len()
+        );
+        for z in zones {
+            line.push_str(&format!(" {}", z));
+        }
+        line.push_str(&format!(" {}", c.touched_weapons.len()));
+        for (typ, id) in &c.touched_weapons {
+            line.push_str(&format!(" {} {}", weapon_field(*typ), id.to_bits()));
+        }
+        lines.push(line);
+    }
+    // so the player is still facing the same way before they next move
+    if let Some(spr) = e.get::<&PlayerSprite>() {
+        lines.push(format!("sprite {} {} {}", spr.n, spr.flipped, spr.climbing));
+    }
+}
+
+fn shot_lines(e: &EntityRef, lines: &mut Vec<String>) {
+    lines.push("shot".to_owned());
+    if let (Some(r), Some(p)) = (e.get::<&IntRect>(), e.get::<&Projectile>()) {
+        let mut line = format!(
+            "projectile {} {} {} {} {} {} {} {}",
+            r.x, r.y, r.w, r.h, p.prec_x, p.prec_y, p.vx, p.vy
+        );
+        for name in shot_markers(e) {
+            line.push_str(&format!(" {}", name));
+        }
+        lines.push(line);
+    }
+    // the enemies it has already been through
+    if let Some(p) = e.get::<&Piercing>() {
+        let mut line = format!("piercing {} {} {}", p.damage, p.max_hits, p.hit.len());
+        for id in &p.hit {
+            line.push_str(&format!(" {}", id.to_bits()));
+        }
+        lines.push(line);
+    }
+    if let Some(spr) = e.get::<&ZapSprite>() {
+        lines.push(format!("zap {} {}", spr.angle, spr.scale));
+    }
+    if let Some(fb) = e.get::<&FireballEffect>() {
+        lines.push(format!("fireball {} {}", fb.r, fb.t));
+    }
+    if let Some(b) = e.get::<&Blast>() {
+        lines.push(format!(
+            "blast {} {} {} {} {} {}",
+            b.centre.x, b.centre.y, b.radius, b.damage, b.knockback, b.hurts_player
+        ));
+    }
+    if let Some(b) = e.get::<&Beam>() {
+        lines.push(format!(
+            "beam {} {} {} {} {} {}",
+            b.from.x, b.from.y, b.dir.x, b.dir.y, b.range, b.damage
+        ));
+    }
+}
+
+pub fn quicksave(resources: &SceneResources) -> Result<String, String> {
+    let world = resources.world_ref.lock().unwrap();
+    if world.get::<&Controller>(resources.player_id).is_err() {
+        return Err("the player is dead".to_owned());
+    }
+    let mut lines = vec![
+        format!("level {}", resources.stats.info.path),
+        format!("seed {}", resources.seed),
+    ];
+    lines.extend(resources.history.inventory().to_lines());
+    for line in resources.persist_inventory().to_lines() {
+        lines.push(format!("carrying {}", line));
+    }
+    let s = &resources.stats;
+    lines.push(format!(
+        "stats {} {} {} {}",
+        s.frames, s.kills, s.items, s.secrets
+    ));
+    lines.push(format!("rng {}", resources.rng.state()));
+    let sel = &resources.selector;
+    lines.push(format!(
+        "selector {} {} {}",
+        sel.timer, sel.offset, sel.hidden
+    ));
+    lines.extend(resources.script_engine.to_lines()?);
+    for e in world.iter() {
+        if !is_shot(&e) && !is_effect(&e) {
+            entity_lines(&e, &mut lines);
+        }
+    }
+    for e in world.iter() {
+        if is_shot(&e) {
+            shot_lines(&e, &mut lines);
+        }
+    }
+    lines.push(String::new());
+    Ok(lines.join("\n"))
+}
+
+pub fn save_quicksave(resources: &SceneResources, storage: &mut Storage) -> Result<(), String> {
+    storage.write(QUICKSAVE_FILE, &quicksave(resources)?)
+}
+
+fn component<'w, T: Component>(
+    world: &'w World,
+    id: Option<Entity>,
+    keyword: &str,
+) -> Result<RefMut<'w, T>, String> {
+    let id = id.ok_or_else(|| format!("{} before any entity", keyword))?;
+    world
+        .get::<&mut T>(id)
+        .map_err(|_| format!("entity {} can't have a {}", id.to_bits(), keyword))
+}
+
+impl Quicksave {
+    pub fn load(storage: &Storage) -> Result<Option<Self>, String> {
+        match storage.read(QUICKSAVE_FILE)? {
+            Some(text) => Self::parse(&text).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // only the lines needed to load the level are checked here, the rest are checked by apply
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut save = Self {
+            level: String::new(),
+            seed: 0,
+            inventory: Inventory::new(),
+            lines: Vec::new(),
+        };
+        for (idx, line) in text.lines().enumerate() {
+            let err = |what: &str| format!("line {} of quicksave: {}", idx + 1, what);
+            let mut parts = line.split_whitespace();
+            let keyword = match parts.next() {
+                Some(k) => k,
+                None => continue,
+            };
+            let args: Vec<&str> = parts.collect();
+            match (keyword, &args[..]) {
+                ("level", [path]) => save.level = (*path).to_owned(),
+                ("seed", [seed]) => save.seed = seed.parse().map_err(|_| err("bad seed"))?,
+                ("weapons" | "ammo", _) => {
+                    save.inventory
+                        .parse_line(keyword, &args)
+                        .map_err(|e| err(&e))?;
+                }
+                _ => save.lines.push((idx + 1, line.to_owned())),
+            }
+        }
+        if save.level.is_empty() {
+            return Err("quicksave doesn't say which level it's for".to_owned());
+        }
+        Ok(save)
+    }
+
+    // put a freshly loaded copy of the level back how it was when the quicksave was made,
+    // which can leave it half done if that fails so it should be thrown away then
+    pub fn apply(&self, resources: &mut SceneResources) -> Result<(), String> {
+        let unseen = resources
+            .world_ref
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.entity())
+            .collect();
+        let mut state = ApplyState {
+            entity: None,
+            unseen,
+            pathless: BTreeSet::new(),
+            order: Vec::new(),
+            shots: Vec::new(),
+            carrying: Inventory::new(),
+        };
+        for (n, line) in &self.lines {
+            apply_line(resources, &mut state, line)
+                .map_err(|e| format!("line {} of quicksave: {}", n, e))?;
+        }
+
+        let mut world = resources.world_ref.lock().unwrap();
+        for id in state.unseen {
+            world.despawn(id).unwrap();
+        }
+        for id in state.pathless {
+            world.remove_one::<PathMotion>(id).unwrap();
+        }
+        restore_order(&mut world, &state.order);
+        for mut shot in state.shots {
+            world.spawn(shot.build());
+        }
+        let centre = world
+            .get::<&IntRect>(resources.player_id)
+            .map_err(|_| "the player isn't in the quicksave".to_owned())?
+            .centre();
+        reset_camera(&mut world, centre);
+        drop(world);
+        resources.weapons = state
+            .carrying
+            .weapon_types
+            .iter()
+            .map(|t| new_weapon(*t))
+            .collect();
+        resources.ammo = state.carrying.ammo;
+        restore_checkpoint(resources);
+        Ok(())
+    }
+}
+
+struct ApplyState {
+    // the entity the lines are about at the moment
+    entity: Option<Entity>,
+    // what's left over once the lines have all been read
+    unseen: BTreeSet<Entity>,
+    pathless: BTreeSet<Entity>,
+    order: Vec<Entity>,
+    shots: Vec<EntityBuilder>,
+    carrying: Inventory,
+}
+
+// queries visit entities in the order the world stores them, which despawning and adding or
+// removing parts shuffles about. wherever a fresh load doesn't match the quicksave, those
+// entities are taken out and put back in the order they were saved in, so that random numbers
+// go to the same enemies and shots hit the same ones as before
+fn restore_order(world: &mut World, order: &[Entity]) {
+    let saved: HashMap<u32, (usize, Entity)> = order
+        .iter()
+        .enumerate()
+        .map(|(n, id)| (id.id(), (n, *id)))
+        .collect();
+    let mut shuffled = Vec::new();
+    for archetype in world.archetypes() {
+        let ids: Vec<(usize, Entity)> = archetype.ids().iter().map(|i| saved[i]).collect();
+        if ids.windows(2).any(|w| w[0].0 > w[1].0) {
+            shuffled.push(ids);
+        }
+    }
+    let mut held = World::new();
+    for mut ids in shuffled {
+        ids.sort_unstable();
+        for (_, id) in &ids {
+            held.spawn_at(*id, world.take(*id).unwrap());
+        }
+        for (_, id) in &ids {
+            world.spawn_at(*id, held.take(*id).unwrap());
+        }
+    }
+}
+
+fn apply_line(
+    resources: &mut SceneResources,
+    state: &mut ApplyState,
+    line: &str,
+) -> Result<(), String> {
+    let mut f = Fields::new(line);
+    let keyword = f.word()?;
+    match keyword {
+        "carrying" => {
+            let kw = f.word()?;
+            if !state.carrying.parse_line(kw, &f.rest())? {
+                return Err(format!("can't be carrying {}", kw));
+            }
+            return Ok(());
+        }
+        "stats" => {
+            let s = &mut resources.stats;
+            s.frames = f.value()?;
+            s.kills = f.value()?;
+            s.items = f.value()?;
+            s.secrets = f.value()?;
+        }
+        "rng" => resources.rng = Rng::from_state(f.value()?),
+        "selector" => {
+            let sel = &mut resources.selector;
+            sel.timer = f.value()?;
+            sel.offset = f.value()?;
+            sel.hidden = f.value()?;
+        }
+        "entity" => {
+            let id = f.entity(&resources.world_ref.lock().unwrap())?;
+            if !state.unseen.remove(&id) {
+                return Err(format!("entity {} is there twice", id.to_bits()));
+            }
+            state.order.push(id);
+            state.entity = Some(id);
+        }
+        "shot" => {
+            state.shots.push(EntityBuilder::new());
+            state.entity = None;
+        }
+        "projectile" | "piercing" | "zap" | "fireball" | "blast" | "beam" => {
+            let shot = state
+                .shots
+                .last_mut()
+                .ok_or_else(|| format!("{} before any shot", keyword))?;
+            apply_shot_line(shot, keyword, &mut f)?;
+        }
+        _ if resources.script_engine.parse_line(keyword, &mut f)? => (),
+        _ => apply_entity_line(resources, state, keyword, &mut f)?,
+    }
+    f.end()
+}
+
+fn apply_entity_line(
+    resources: &mut SceneResources,
+    state: &mut ApplyState,
+    keyword: &str,
+    f: &mut Fields,
+) -> Result<(), String> {
+    let mut world = resources.world_ref.lock().unwrap();
+    let id = state.entity;
+    match keyword {
+        "rect" => {
+            let mut r = component::<IntRect>(&world, id, keyword)?;
+            *r = IntRect::new(f.value()?, f.value()?, f.value()?, f.value()?);
+        }
+        "actor" => {
+            let mut a = component::<Actor>(&world, id, keyword)?;
+            a.prec_x = f.value()?;
+            a.prec_y = f.value()?;
+            a.vx = f.value()?;
+            a.vy = f.value()?;
+            a.grounded = f.value()?;
+            a.crushed = f.value()?;
+            a.drag = f.value()?;
+            a.gravity = f.value()?;
+            a.ground = TileFlags::from_bits_truncate(f.value()?);
+            a.touching = TileFlags::from_bits_truncate(f.value()?);
+            a.liquid = match f.value_opt::<String>()? {
+                Some(name) => Some(
+                    LiquidKind::from_name(&name)
+                        .ok_or_else(|| format!("unknown liquid {}", name))?,
+                ),
+                None => None,
+            };
+            a.wall = f.value()?;
+        }
+        "body" => {
+            let mut body = component::<TileBody>(&world, id, keyword)?;
+            let id = id.unwrap();
+            if body.indexed {
+                resources.body_index.remove_at(id, &body.get_rect());
+            }
+            (body.x, body.y) = (f.value()?, f.value()?);
+            if body.indexed {
+                resources.body_index.insert_at(id, &body.get_rect());
+            }
+            drop(body);
+            // until a path line says otherwise
+            if world.satisfies::<&PathMotion>(id).unwrap() {
+                state.pathless.insert(id);
+            }
+        }
+        "path" => {
+            component::<TileBody>(&world, id, keyword)?;
+            let id = id.unwrap();
+            let motion_type = parse_motion(f)?;
+            let mut pm = PathMotion::new(0.0, 0.0, &[], f.value()?, motion_type);
+            pm.prec_x = f.value()?;
+            pm.prec_y = f.value()?;
+            pm.next_node = f.value()?;
+            while !f.is_empty() {
+                pm.offsets.push(vec2(f.value()?, f.value()?));
+            }
+            if pm.next_node >= pm.offsets.len() {
+                return Err("path doesn't have that many nodes".to_owned());
+            }
+            // replaced in place where possible, to keep bodies in the same order
+            if state.pathless.remove(&id) {
+                *world.get::<&mut PathMotion>(id).unwrap() = pm;
+            } else {
+                world.insert_one(id, pm).unwrap();
+            }
+        }
+        "enemy" => {
+            let mut h = component::<EnemyHittable>(&world, id, keyword)?;
+            h.hp = f.value()?;
+            h.was_hit = f.value()?;
+        }
+        "dog" => {
+            let mut dog = component::<DogBehaviour>(&world, id, keyword)?;
+            dog.dir = f.value()?;
+            dog.jump_y = f.value_opt()?;
+        }
+        "parrot" => {
+            let mut p = component::<ParrotBehaviour>(&world, id, keyword)?;
+            let name = f.word()?;
+            p.state = PARROT_STATES
+                .iter()
+                .find(|(_, n)| *n == name)
+                .map(|(s, _)| *s)
+                .ok_or_else(|| format!("unknown parrot state {}", name))?;
+            p.state_timer = f.value()?;
+            p.attack_timer = f.value()?;
+            p.facing = f.value()?;
+        }
+        "pickup" => component::<Pickup>(&world, id, keyword)?.touched = f.value()?,
+        "weapon" => {
+            let mut wp = component::<WeaponPickup>(&world, id, keyword)?;
+            wp.typ = f.weapon()?;
+            wp.touched = f.value()?;
+        }
+        "switch" => component::<Switch>(&world, id, keyword)?.enabled = f.value()?,
+        "zone" => {
+            component::<TriggerZone>(&world, id, keyword)?.secrecy = match f.word()? {
+                "none" => Secrecy::NotSecret,
+                "hidden" => Secrecy::Hidden,
+                "found" => Secrecy::Found,
+                other => return Err(format!("unknown secrecy {}", other)),
+            }
+        }
+        "checkpoint" => component::<Checkpoint>(&world, id, keyword)?.active = f.value()?,
+        "player" => {
+            let mut c = component::<Controller>(&world, id, keyword)?;
+            c.jump_frames = f.value()?;
+            c.air_frames = f.value()?;
+            c.jump_press_frames = f.value()?;
+            c.climbing = f.value()?;
+            c.wall_jump = f.value()?;
+            c.facing = f.value()?;
+            c.fire_timer = f.value()?;
+            c.hurt_timer = f.value()?;
+            c.hp = f.value()?;
+            c.god_mode = f.value()?;
+            c.zones.clear();
+            for _ in 0..f.value::<usize>()? {
+                c.zones.insert(f.word()?.to_owned());
+            }
+            c.touched_weapons.clear();
+            for _ in 0..f.value::<usize>()? {
+                let typ = f.weapon()?;
+                c.touched_weapons.insert(typ, f.id()?);
+            }
+        }
+        "sprite" => {
+            let mut spr = component::<PlayerSprite>(&world, id, keyword)?;
+            spr.n = f.value()?;
+            spr.flipped = f.value()?;
+            spr.climbing = f.value()?;
+        }
+        other => return Err(format!("unknown line type {}", other)),
+    }
+    Ok(())
+}
+
+fn apply_shot_line(shot: &mut EntityBuilder, keyword: &str, f: &mut Fields) -> Result<(), String> {
+    match keyword {
+        "projectile" => {
+            let rect = IntRect::new(f.value()?, f.value()?, f.value()?, f.value()?);
+            let mut proj = Projectile::new(&rect, 0.0, 0.0);
+            proj.prec_x = f.value()?;
+            proj.prec_y = f.value()?;
+            proj.vx = f.value()?;
+            proj.vy = f.value()?;
+            shot.add(rect).add(proj);
+            while !f.is_empty() {
+                add_shot_marker(shot, f.word()?)?;
+            }
+        }
+        "piercing" => {
+            let damage = f.value()?;
+            let max_hits = f.value()?;
+            let mut hit = Vec::new();
+            for _ in 0..f.value::<usize>()? {
+                hit.push(f.id()?);
+            }
+            shot.add(Piercing {
+                damage,
+                max_hits,
+                hit,
+            });
+        }
+        "zap" => {
+            let mut spr = ZapSprite::aimed(f.value()?);
+            spr.scale = f.value()?;
+            shot.add(spr);
+        }
+        "fireball" => {
+            let mut fb = FireballEffect::new(f.value()?);
+            fb.t = f.value()?;
+            shot.add(fb);
+        }
+        "blast" => {
+            let mut blast = Blast::new(0, 0, 0.0, 0, 0.0);
+            blast.centre = vec2(f.value()?, f.value()?);
+            blast.radius = f.value()?;
+            blast.damage = f.value()?;
+            blast.knockback = f.value()?;
+            blast.hurts_player = f.value()?;
+            shot.add(blast);
+        }
+        "beam" => {
+            let from = vec2(f.value()?, f.value()?);
+            let dir = vec2(f.value()?, f.value()?);
+            shot.add(Beam::new(from, dir, f.value()?, f.value()?));
+        }
+        other => return Err(format!("unknown line type {}", other)),
+    }
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use crate::draw::{draw_sprites, draw_tiles};
 use crate::level::LevelInfo;
+use crate::loader::LoadError;
 use crate::messages::Messages;
 use crate::player::Controller;
 use crate::profile::{Phase, Profiler};
 use crate::resources::{GlobalAssets, SceneResources};
+use crate::rng::Rng;
+use crate::save::LevelRecord;
 use crate::scene::Scene;
-use crate::stats::LevelStats;
+use crate::stats::{format_time, percent, LevelStats};
 use crate::transition::{new_transition, TransitionEffect, TransitionEffectType};
 use crate::vfx::draw_vfx;
 use crate::visibility::draw_visibility;
@@ -121,22 +124,23 @@ impl Renderer {
         }
     }
 
-    pub(crate) fn render_scene(
-        &self,
-        scene: &Scene,
-        assets: &GlobalAssets,
-        profiler: &mut Profiler,
-    ) {
+    pub fn render_scene(&self, scene: &Scene, assets: &GlobalAssets, profiler: &mut Profiler) {
         // draw the current scene
         match scene {
+            Scene::Continue(info, _) => {
+                self.draw_continue(info, assets);
+            }
             Scene::PreLevel(n, _, _) => {
                 self.draw_prelevel(n, assets);
             }
             Scene::PlayLevel(resources) => {
                 self.draw_world(resources, assets, profiler);
             }
-            Scene::PostLevel(stats, _) => {
-                self.draw_postlevel(stats);
+            Scene::PostLevel(stats, _, previous) => {
+                self.draw_postlevel(stats, previous.as_ref());
+            }
+            Scene::LoadError(info, _, err) => {
+                self.draw_load_error(info, err, assets);
             }
         }
 
@@ -190,7 +194,7 @@ impl Renderer {
         );
     }
 
-    pub(crate) fn render_loading(&self) {
+    pub fn render_loading(&self) {
         gl_use_default_material();
         set_camera(&get_camera_for_target(
             &self.draw_target,
@@ -251,7 +255,30 @@ impl Renderer {
         );
     }
 
-    pub(crate) fn draw_postlevel(&self, stats: &LevelStats) {
+    pub(crate) fn draw_continue(&self, level_info: &LevelInfo, assets: &GlobalAssets) {
+        gl_use_default_material();
+        set_camera(&get_camera_for_target(
+            &self.draw_target,
+            vec2(self.width / 2., self.height / 2.),
+            Origin::TopLeft,
+        ));
+        let wvdc = WALL_VISION_DEPTH.ceil();
+        for x in 0..8 {
+            for y in 0..5 {
+                draw_texture(
+                    assets.interstitial,
+                    wvdc + x as f32 * 40.0,
+                    wvdc + y as f32 * 40.0,
+                    WHITE,
+                );
+            }
+        }
+        self.draw_centred_text("Continue from", 16, 72.0);
+        self.draw_centred_text(&level_info.name, 32, 100.0);
+        self.draw_centred_text("Z: continue    X: new game", 16, 176.0);
+    }
+
+    pub(crate) fn draw_postlevel(&self, stats: &LevelStats, previous: Option<&LevelRecord>) {
         gl_use_default_material();
         set_camera(&get_camera_for_target(
             &self.draw_target,
@@ -259,9 +286,9 @@ impl Renderer {
             Origin::TopLeft,
         ));
         let wvdc = WALL_VISION_DEPTH.ceil();
-        if self.transition.is_some() {
+        if let Some((target, _)) = &self.transition {
             draw_texture(
-                self.transition.as_ref().unwrap().0.texture,
+                target.texture,
                 wvdc,
                 wvdc,
                 Color {
@@ -272,24 +299,91 @@ impl Renderer {
                 },
             );
         }
-        self.draw_centred_text("Completed", 16, 72.0);
-        self.draw_centred_text(&stats.info.name, 32, 100.0);
-        self.draw_centred_text(&format!("Time: {}", stats.pretty_time()), 16, 128.0);
-        self.draw_centred_text(
-            &format!("Enemies defeated: {}/{}", stats.kills, stats.max_kills),
-            16,
-            144.0,
-        );
-        self.draw_centred_text(
-            &format!("Items found: {}/{}", stats.items, stats.max_items),
-            16,
-            160.0,
-        );
-        self.draw_centred_text(
-            &format!("Secrets entered: {}/{}", stats.secrets, stats.max_secrets),
-            16,
-            176.0,
-        );
+        let heading = match previous {
+            Some(r) => format!("Completed {} times", r.completions + 1),
+            None => "Completed".to_owned(),
+        };
+        self.draw_centred_text(&heading, 16, 60.0);
+        self.draw_centred_text(&stats.info.name, 32, 88.0);
+        let time = match previous {
+            Some(r) if stats.frames < r.best_frames => {
+                format!("Time: {}  New record!", stats.pretty_time())
+            }
+            Some(r) => format!(
+                "Time: {}  Best: {}",
+                stats.pretty_time(),
+                format_time(r.best_frames)
+            ),
+            // nothing to beat on a first clear
+            None => format!("Time: {}", stats.pretty_time()),
+        };
+        self.draw_centred_text(&time, 16, 112.0);
+        if let Some(par) = stats.info.par {
+            let comparison = if stats.frames <= par {
+                format!("beaten by {}", format_time(par - stats.frames))
+            } else {
+                format!("over by {}", format_time(stats.frames - par))
+            };
+            self.draw_centred_text(
+                &format!("Par: {} ({})", format_time(par), comparison),
+                16,
+                128.0,
+            );
+        }
+        let lines = [
+            (
+                "Enemies defeated",
+                stats.kills,
+                stats.max_kills,
+                previous.map(|r| r.best_kills),
+            ),
+            (
+                "Items found",
+                stats.items,
+                stats.max_items,
+                previous.map(|r| r.best_items),
+            ),
+            (
+                "Secrets entered",
+                stats.secrets,
+                stats.max_secrets,
+                previous.map(|r| r.best_secrets),
+            ),
+        ];
+        for (i, (what, n, max, best)) in lines.iter().enumerate() {
+            let mut text = format!("{}: {}/{}", what, n, max);
+            if matches!(best, Some(b) if percent(*n, *max) > *b) {
+                text.push_str("  New best!");
+            }
+            self.draw_centred_text(&text, 16, 148.0 + i as f32 * 16.0);
+        }
+    }
+
+    pub(crate) fn draw_load_error(
+        &self,
+        level_info: &LevelInfo,
+        err: &LoadError,
+        assets: &GlobalAssets,
+    ) {
+        gl_use_default_material();
+        set_camera(&get_camera_for_target(
+            &self.draw_target,
+            vec2(self.width / 2., self.height / 2.),
+            Origin::TopLeft,
+        ));
+        clear_background(Color::new(0.2, 0.0, 0.0, 1.0));
+        self.draw_centred_text("Couldn't load", 16, 40.0);
+        self.draw_centred_text(&level_info.name, 32, 68.0);
+        let mut y = 92.0;
+        for line in wrap_text(&err.to_string(), 36).iter().take(5) {
+            self.draw_centred_text(line, 16, y);
+            y += 14.0;
+        }
+        if level_info.number.is_some() && assets.get_previous_level(level_info).is_none() {
+            self.draw_centred_text("Z: retry", 16, 176.0);
+        } else {
+            self.draw_centred_text("Z: retry    X: go back", 16, 176.0);
+        }
     }
 
     pub fn draw_centred_text(&self, text: &str, size: u16, y: f32) {
@@ -520,7 +614,7 @@ impl Renderer {
         }
     }
 
-    pub fn start_transition(&mut self, typ: TransitionEffectType) {
+    pub fn start_transition(&mut self, typ: TransitionEffectType, rng: &mut Rng) {
         let ff = render_target(self.final_width as u32, self.final_height as u32);
         ff.texture.set_filter(FilterMode::Nearest);
         gl_use_default_material();
@@ -544,7 +638,7 @@ impl Renderer {
                 ..Default::default()
             },
         );
-        self.transition = Some((ff, new_transition(typ)));
+        self.transition = Some((ff, new_transition(typ, rng)));
     }
 
     pub fn transition_finished(&self) -> bool {
@@ -561,6 +655,25 @@ impl Renderer {
     }
 }
 
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.len() + word.len() >= width {
+            lines.push(current);
+            current = String::new();
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
 pub fn load_flash_material() -> Material {
     let bs = BlendState::new(
         Equation::Add,
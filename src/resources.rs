@@ -1,16 +1,21 @@
+use crate::checkpoint::{RespawnMode, Snapshot};
+use crate::demo::DemoRecorder;
 use crate::index::SpatialIndex;
 use crate::level::{load_level_info, LevelInfo};
 use crate::messages::Messages;
+use crate::movement::MovementConfig;
 use crate::render::load_flash_material;
+use crate::rng::Rng;
 use crate::scene::Scene;
 use crate::script::ScriptEngine;
 use crate::stats::LevelStats;
 use crate::transition::TransitionEffectType;
 use crate::weapon::{AmmoQuantity, AmmoType, Weapon, WeaponSelectorUI, WeaponType};
+use enum_iterator::all;
 use enum_map::EnumMap;
 use hecs::{Entity, World};
 use macroquad::prelude::*;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::num::NonZeroU8;
 use std::sync::{Arc, Mutex};
 
@@ -61,13 +66,22 @@ pub struct SceneResources {
     pub death_timer: Option<NonZeroU8>,
     pub draw_order: Vec<Entity>,
     pub body_index: SpatialIndex,
-    pub tileset_info: TilesetInfo,
+    pub tilesets: Vec<TilesetInfo>,
     pub messages: Messages,
     pub selector: WeaponSelectorUI,
     pub stats: LevelStats,
-    pub triggers: HashSet<String>,
+    pub triggers: BTreeSet<String>,
     pub weapons: VecDeque<Box<dyn Weapon>>,
     pub ammo: EnumMap<AmmoType, AmmoQuantity>,
+    pub seed: u64,
+    pub rng: Rng,
+    // particles and smoke, kept apart so they can't change how a level plays out
+    pub cosmetic_rng: Rng,
+    pub movement: MovementConfig,
+    pub respawn: RespawnMode,
+    pub checkpoint: Option<Snapshot>,
+    // every tick of input since the level started, for recording demos
+    pub history: DemoRecorder,
 }
 
 impl SceneResources {
@@ -78,6 +92,15 @@ impl SceneResources {
             is_default: false,
         }
     }
+
+    // tilesets are in order of first_gid, so a tile belongs to the last one starting at or before it
+    pub fn tileset_for(&self, gid: u32) -> &TilesetInfo {
+        self.tilesets
+            .iter()
+            .rev()
+            .find(|ts| ts.first_gid <= gid)
+            .unwrap_or(&self.tilesets[0])
+    }
 }
 
 #[derive(Clone)]
@@ -95,6 +118,59 @@ impl Inventory {
             is_default: true,
         }
     }
+
+    // weapons by their position in WeaponType and ammo in AmmoType order, as used in demos and saves
+    pub fn to_lines(&self) -> Vec<String> {
+        let weapons: Vec<String> = self
+            .weapon_types
+            .iter()
+            .map(|t| {
+                all::<WeaponType>()
+                    .position(|w| w == *t)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        let ammo: Vec<String> = all::<AmmoType>()
+            .map(|t| self.ammo[t].to_string())
+            .collect();
+        vec![
+            format!("weapons {}", weapons.join(" ")),
+            format!("ammo {}", ammo.join(" ")),
+        ]
+    }
+
+    // returns false if the line was about something else
+    pub fn parse_line(&mut self, keyword: &str, args: &[&str]) -> Result<bool, String> {
+        let numbers = args
+            .iter()
+            .map(|a| a.parse::<usize>().map_err(|_| "bad number".to_owned()))
+            .collect::<Result<Vec<usize>, String>>();
+        match keyword {
+            "weapons" => {
+                let all_types: Vec<WeaponType> = all::<WeaponType>().collect();
+                self.weapon_types = numbers?
+                    .iter()
+                    .map(|n| all_types.get(*n).copied())
+                    .collect::<Option<Vec<WeaponType>>>()
+                    .ok_or_else(|| "unknown weapon".to_owned())?;
+            }
+            "ammo" => {
+                let numbers = numbers?;
+                if numbers.len() != all::<AmmoType>().count() {
+                    return Err("wrong number of ammo types".to_owned());
+                }
+                let mut ammo = EnumMap::default();
+                for (typ, n) in all::<AmmoType>().zip(numbers) {
+                    ammo[typ] = n as AmmoQuantity;
+                }
+                self.ammo = ammo;
+            }
+            _ => return Ok(false),
+        }
+        self.is_default = false;
+        Ok(true)
+    }
 }
 
 #[derive(Clone)]
@@ -103,4 +179,64 @@ pub struct TilesetInfo {
     pub tile_width: u32,
     pub tile_height: u32,
     pub columns: u32,
+    pub first_gid: u32,
+    pub animations: HashMap<u32, TileAnimation>,
+}
+
+// a tile that cycles through other tiles from its tileset, timed in ticks
+#[derive(Clone)]
+pub struct TileAnimation {
+    frames: Vec<(u32, u32)>,
+    length: u32,
+}
+
+impl TileAnimation {
+    pub fn new(frames: Vec<(u32, u32)>) -> Self {
+        let length = frames.iter().map(|(_, ticks)| ticks).sum();
+        Self { frames, length }
+    }
+
+    // tiled gives durations in milliseconds, but every frame has to last at least one tick
+    pub fn from_millis(frames: impl Iterator<Item = (u32, u32)>) -> Self {
+        Self::new(
+            frames
+                .map(|(id, ms)| (id, (ms * 30 / 1000).max(1)))
+                .collect(),
+        )
+    }
+
+    // every animation runs off the same tick count so that matching tiles stay in step
+    pub fn tile_at(&self, tick: u32) -> u32 {
+        let mut t = tick % self.length;
+        for (id, ticks) in &self.frames {
+            if t < *ticks {
+                return *id;
+            }
+            t -= ticks;
+        }
+        self.frames[0].0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TileAnimation;
+
+    #[test]
+    fn tile_animation_frames() {
+        let anim = TileAnimation::new(vec![(5, 3), (6, 1), (7, 2)]);
+        let tiles: Vec<u32> = (0..8).map(|t| anim.tile_at(t)).collect();
+        assert_eq!(tiles, vec![5, 5, 5, 6, 7, 7, 5, 5]);
+        // long after the start it's still in step
+        assert_eq!(anim.tile_at(6 * 1000 + 3), 6);
+        assert_eq!(anim.tile_at(u32::MAX), anim.tile_at(u32::MAX % 6));
+    }
+
+    #[test]
+    fn tile_animation_from_millis() {
+        // 30 ticks a second, rounding down but never to nothing
+        let anim = TileAnimation::from_millis(vec![(1, 1000), (2, 100), (3, 10)].into_iter());
+        assert_eq!(anim.frames, vec![(1, 30), (2, 3), (3, 1)]);
+        assert_eq!(anim.length, 34);
+    }
 }
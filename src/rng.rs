@@ -0,0 +1,112 @@
+// each level seeds its own generators so that runs can be replayed
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+// PCG32, the same algorithm as quad_rand
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    // for quicksaves, which need to carry on from exactly the same place
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn from_state(state: u64) -> Self {
+        Self { state }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    pub fn gen_range<T: RandomRange>(&mut self, low: T, high: T) -> T {
+        T::gen_range(self, low, high)
+    }
+
+    pub fn with_prob(&mut self, p: f32) -> bool {
+        self.gen_range(0.0, 1.0) < p
+    }
+
+    pub fn sign(&mut self) -> f32 {
+        if self.next_u32().is_multiple_of(2) {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+}
+
+pub trait RandomRange: Sized {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self;
+}
+
+impl RandomRange for f32 {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        // only the top 24 bits fit in an f32 exactly, so the result can never round up to high
+        low + (high - low) * ((rng.next_u32() >> 8) as f32 / (1 << 24) as f32)
+    }
+}
+
+// integer ranges are half-open, like quad_rand's almost are
+impl RandomRange for i32 {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+        // the width of the range can be more than i32::MAX, but it always fits in a u32
+        low.wrapping_add((rng.next_u32() % high.wrapping_sub(low) as u32) as i32)
+    }
+}
+
+impl RandomRange for u8 {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+        low + (rng.next_u32() % (high - low) as u32) as u8
+    }
+}
+
+// combine the seed for a whole session with the level path, using FNV-1a so that the
+// result doesn't depend on the platform or compiler version
+pub fn level_seed(path: &str, seed: u64) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in path.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash ^ seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_int_ranges() {
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let n = rng.gen_range(i32::MIN, i32::MAX);
+            assert!(n < i32::MAX);
+            let n = rng.gen_range(-5, 5);
+            assert!((-5..5).contains(&n));
+        }
+        assert_eq!(rng.gen_range(3, 3), 3);
+    }
+}
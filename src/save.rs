@@ -0,0 +1,226 @@
+use crate::resources::Inventory;
+use crate::stats::{percent, LevelStats};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+pub const SAVE_FILE: &str = "save.txt";
+
+// somewhere to keep small text files between runs of the game
+pub enum Storage {
+    // files in a directory
+    File(PathBuf),
+    // for tests, and anywhere else that shouldn't touch the disk
+    Memory(HashMap<String, String>),
+    // the browser's local storage, through functions added to the page in index.html
+    #[cfg(target_arch = "wasm32")]
+    LocalStorage,
+}
+
+impl Storage {
+    #[cfg(target_arch = "wasm32")]
+    pub fn for_platform() -> Self {
+        Self::LocalStorage
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn for_platform() -> Self {
+        Self::File(PathBuf::from("."))
+    }
+
+    // nothing having been saved yet isn't an error
+    pub fn read(&self, name: &str) -> Result<Option<String>, String> {
+        match self {
+            Self::File(dir) => match std::fs::read_to_string(dir.join(name)) {
+                Ok(text) => Ok(Some(text)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(format!("Couldn't read {}: {}", name, e)),
+            },
+            Self::Memory(files) => Ok(files.get(name).cloned()),
+            #[cfg(target_arch = "wasm32")]
+            Self::LocalStorage => Ok(local_storage::get(name)),
+        }
+    }
+
+    pub fn write(&mut self, name: &str, text: &str) -> Result<(), String> {
+        match self {
+            Self::File(dir) => std::fs::write(dir.join(name), text)
+                .map_err(|e| format!("Couldn't write {}: {}", name, e)),
+            Self::Memory(files) => {
+                files.insert(name.to_owned(), text.to_owned());
+                Ok(())
+            }
+            #[cfg(target_arch = "wasm32")]
+            Self::LocalStorage => {
+                local_storage::set(name, text);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod local_storage {
+    extern "C" {
+        // returns the length in bytes of the utf-8 value, or -1 if there isn't one
+        fn storage_length(key: *const u8, key_len: u32) -> i32;
+        fn storage_read(key: *const u8, key_len: u32, buf: *mut u8);
+        fn storage_write(key: *const u8, key_len: u32, value: *const u8, value_len: u32);
+    }
+
+    pub fn get(key: &str) -> Option<String> {
+        let len = unsafe { storage_length(key.as_ptr(), key.len() as u32) };
+        if len < 0 {
+            return None;
+        }
+        let mut buf = vec![0u8; len as usize];
+        unsafe { storage_read(key.as_ptr(), key.len() as u32, buf.as_mut_ptr()) };
+        String::from_utf8(buf).ok()
+    }
+
+    pub fn set(key: &str, value: &str) {
+        unsafe {
+            storage_write(
+                key.as_ptr(),
+                key.len() as u32,
+                value.as_ptr(),
+                value.len() as u32,
+            )
+        };
+    }
+}
+
+// how well the player has ever done on a level, where each best can come from a different attempt
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LevelRecord {
+    pub completions: u32,
+    pub best_frames: u32,
+    // percentages, so they still make sense if the level changes
+    pub best_kills: u32,
+    pub best_items: u32,
+    pub best_secrets: u32,
+}
+
+impl LevelRecord {
+    fn new(stats: &LevelStats) -> Self {
+        Self {
+            completions: 1,
+            best_frames: stats.frames,
+            best_kills: percent(stats.kills, stats.max_kills),
+            best_items: percent(stats.items, stats.max_items),
+            best_secrets: percent(stats.secrets, stats.max_secrets),
+        }
+    }
+
+    fn add(&mut self, stats: &LevelStats) {
+        let other = Self::new(stats);
+        self.completions += 1;
+        self.best_frames = self.best_frames.min(other.best_frames);
+        self.best_kills = self.best_kills.max(other.best_kills);
+        self.best_items = self.best_items.max(other.best_items);
+        self.best_secrets = self.best_secrets.max(other.best_secrets);
+    }
+}
+
+// progress through the levels in levels.txt, stored as text:
+//   level <path> (the level to continue from)
+//   weapons <index>... / ammo <n>... (what the player carries into it)
+//   record <path> <completions> <best frames> <best kill %> <best item %> <best secret %>
+pub struct CampaignSave {
+    pub level: Option<String>,
+    pub inventory: Inventory,
+    pub records: BTreeMap<String, LevelRecord>,
+}
+
+impl CampaignSave {
+    pub fn new() -> Self {
+        Self {
+            level: None,
+            inventory: Inventory::new(),
+            records: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(storage: &Storage) -> Result<Self, String> {
+        match storage.read(SAVE_FILE)? {
+            Some(text) => Self::parse(&text),
+            None => Ok(Self::new()),
+        }
+    }
+
+    pub fn save(&self, storage: &mut Storage) -> Result<(), String> {
+        storage.write(SAVE_FILE, &self.to_text())
+    }
+
+    // call when a level is won, with where the player goes next and what they take with them
+    pub fn complete_level(&mut self, stats: &LevelStats, next_level: &str, inv: Inventory) {
+        self.records
+            .entry(stats.info.path.clone())
+            .and_modify(|r| r.add(stats))
+            .or_insert_with(|| LevelRecord::new(stats));
+        self.level = Some(next_level.to_owned());
+        self.inventory = inv;
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(level) = &self.level {
+            lines.push(format!("level {}", level));
+            lines.extend(self.inventory.to_lines());
+        }
+        for (path, r) in &self.records {
+            lines.push(format!(
+                "record {} {} {} {} {} {}",
+                path, r.completions, r.best_frames, r.best_kills, r.best_items, r.best_secrets
+            ));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut save = Self::new();
+        for (idx, line) in text.lines().enumerate() {
+            let err = |what: &str| format!("line {} of save: {}", idx + 1, what);
+            let mut parts = line.split_whitespace();
+            let keyword = match parts.next() {
+                Some(k) => k,
+                None => continue,
+            };
+            let args: Vec<&str> = parts.collect();
+            match (keyword, &args[..]) {
+                ("level", [path]) => save.level = Some((*path).to_owned()),
+                ("record", [path, numbers @ ..]) => {
+                    let numbers: Vec<u32> = numbers
+                        .iter()
+                        .map(|a| a.parse().map_err(|_| err("bad number")))
+                        .collect::<Result<_, _>>()?;
+                    match numbers[..] {
+                        [completions, best_frames, best_kills, best_items, best_secrets] => {
+                            let r = LevelRecord {
+                                completions,
+                                best_frames,
+                                best_kills,
+                                best_items,
+                                best_secrets,
+                            };
+                            save.records.insert((*path).to_owned(), r);
+                        }
+                        _ => return Err(err("record needs a level and five numbers")),
+                    }
+                }
+                ("level", _) => return Err(err("level needs a path")),
+                ("record", _) => return Err(err("record needs a level and five numbers")),
+                _ => {
+                    if !save
+                        .inventory
+                        .parse_line(keyword, &args)
+                        .map_err(|e| err(&e))?
+                    {
+                        return Err(err(&format!("unknown line type {}", keyword)));
+                    }
+                }
+            }
+        }
+        Ok(save)
+    }
+}
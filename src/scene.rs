@@ -1,23 +1,22 @@
 use crate::level::LevelInfo;
-use crate::loader::load_level;
+use crate::loader::{load_level, LoadError};
 use crate::resources::Inventory;
+use crate::save::LevelRecord;
 use crate::{resources::SceneResources, stats::LevelStats};
 use macroquad::experimental::coroutines::{start_coroutine, Coroutine};
 
-// eventually there will be variants whose names don't end in "...Level"
-#[allow(clippy::enum_variant_names)]
+#[allow(clippy::large_enum_variant)]
 pub enum Scene {
-    PreLevel(LevelInfo, Coroutine<Result<Scene, String>>, bool),
+    // offered on launch when there's a saved campaign to continue
+    Continue(LevelInfo, Inventory),
+    PreLevel(LevelInfo, Coroutine<Scene>, bool),
     PlayLevel(SceneResources),
-    PostLevel(LevelStats, Inventory),
+    // with the record for the level from before this attempt, if it's part of the campaign
+    PostLevel(LevelStats, Inventory, Option<LevelRecord>),
+    LoadError(LevelInfo, Inventory, LoadError),
 }
 
-pub async fn new_prelevel(info: LevelInfo, inv: Inventory, fast: bool) -> Scene {
-    let coro: Coroutine<Result<Scene, String>> = start_coroutine(load_level(info.clone(), inv));
-    if coro.is_done() {
-        let res = coro.retrieve();
-        assert!(res.is_some());
-        assert!(res.unwrap().is_ok());
-    }
+pub async fn new_prelevel(info: LevelInfo, inv: Inventory, seed: u64, fast: bool) -> Scene {
+    let coro: Coroutine<Scene> = start_coroutine(load_level(info.clone(), inv, seed));
     Scene::PreLevel(info, coro, fast)
 }
@@ -1,10 +1,12 @@
+use crate::files::AssetSource;
+use crate::loader::LoadError;
 use crate::physics::{PathMotion, PathMotionType, TileBody};
+use crate::quicksave::{motion_fields, parse_motion, Fields};
 use crate::switch::Switch;
 use hecs::{Entity, World};
-use macroquad::file::load_string;
 use rhai::packages::{Package, StandardPackage};
 use rhai::plugin::*;
-use rhai::{def_package, Engine, FnPtr, Scope, AST};
+use rhai::{def_package, Dynamic, Engine, FnPtr, Scope, AST};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
@@ -20,9 +22,13 @@ impl ScriptEntityProxy {
     }
 }
 
+#[derive(Clone)]
 pub struct ScriptFlags {
     win: bool,
-    queued_funcs: Vec<(rhai::INT, FnPtr)>,
+    wall_jump: bool,
+    // each function's name and the values it captured rather than the FnPtr itself, since
+    // there's no way to make a FnPtr for a closure again from its name after a quickload
+    queued_funcs: Vec<(rhai::INT, String, Vec<Dynamic>)>,
     new_popups: Vec<String>,
 }
 
@@ -30,6 +36,7 @@ impl ScriptFlags {
     fn new() -> Self {
         Self {
             win: false,
+            wall_jump: false,
             queued_funcs: Vec::new(),
             new_popups: Vec::new(),
         }
@@ -87,12 +94,20 @@ mod script_interface {
     }
 
     pub fn after_frames(this: &mut Flags, n: rhai::INT, func: FnPtr) {
-        this.lock().unwrap().queued_funcs.push((n, func));
+        this.lock().unwrap().queued_funcs.push((
+            n,
+            func.fn_name().to_owned(),
+            func.curry().to_vec(),
+        ));
     }
 
     pub fn win(this: &mut Flags) {
         this.lock().unwrap().win = true;
     }
+
+    pub fn enable_wall_jump(this: &mut Flags) {
+        this.lock().unwrap().wall_jump = true;
+    }
 }
 
 def_package! {
@@ -103,8 +118,17 @@ def_package! {
     }
 }
 
+// the variables a script can change along with its flags, so that a level can be put back
+// how it was at a checkpoint
+#[derive(Clone)]
+pub struct ScriptState {
+    scope: Scope<'static>,
+    flags: ScriptFlags,
+}
+
 pub struct ScriptEngine {
     engine: Engine,
+    world_ref: Arc<Mutex<World>>,
     scope: Scope<'static>,
     ast: Option<AST>,
     flags: Arc<Mutex<ScriptFlags>>,
@@ -135,18 +159,39 @@ impl ScriptEngine {
 
         Self {
             engine,
+            world_ref,
             scope,
             ast: None,
             flags,
         }
     }
 
-    pub async fn load_file(&mut self, path: &str) {
-        self.ast = Some(
-            self.engine
-                .compile(load_string(path).await.unwrap())
-                .unwrap(),
-        );
+    // the names every level's scope starts with, before the map adds its own
+    pub(crate) fn builtin_names() -> Vec<String> {
+        let world_ref = Arc::new(Mutex::new(World::new()));
+        let se = Self::new(world_ref, Default::default(), Default::default());
+        se.scope
+            .iter_raw()
+            .map(|(name, _, _)| name.to_owned())
+            .collect()
+    }
+
+    pub async fn load_file(&mut self, source: &AssetSource, path: &str) -> Result<(), LoadError> {
+        let text = source
+            .load_string(path)
+            .await
+            .map_err(|_| LoadError::MissingFile(path.to_owned()))?;
+        let ast = self
+            .engine
+            .compile(text)
+            .map_err(|err| LoadError::ScriptCompile {
+                path: path.to_owned(),
+                message: err.0.to_string(),
+                line: err.1.line().unwrap_or(0),
+                column: err.1.position().unwrap_or(0),
+            })?;
+        self.ast = Some(ast);
+        Ok(())
     }
 
     pub fn call_entry_point(&mut self, name: &str) {
@@ -168,16 +213,18 @@ impl ScriptEngine {
     pub fn schedule_queued_funcs(&mut self) {
         let mut context = self.flags.lock().unwrap();
         let mut funcs = Vec::new();
-        for (n, f) in &mut context.queued_funcs {
+        for (n, name, curry) in &mut context.queued_funcs {
             *n -= 1;
             if *n == 0 {
-                funcs.push(f.clone());
+                funcs.push((name.clone(), curry.clone()));
             }
         }
-        context.queued_funcs.retain(|(n, _)| *n > 0);
+        context.queued_funcs.retain(|(n, _, _)| *n > 0);
         drop(context);
-        for f in funcs {
-            f.call::<()>(&self.engine, self.ast.as_ref().unwrap(), ())
+        // a closure's captured values come before any arguments of its own
+        for (name, curry) in funcs {
+            self.engine
+                .call_fn::<()>(&mut self.scope, self.ast.as_ref().unwrap(), &name, curry)
                 .unwrap();
         }
     }
@@ -189,4 +236,164 @@ impl ScriptEngine {
     pub fn win_flag(&self) -> bool {
         self.flags.lock().unwrap().win
     }
+
+    pub fn save_state(&self) -> ScriptState {
+        ScriptState {
+            scope: self.scope.clone(),
+            flags: self.flags.lock().unwrap().clone(),
+        }
+    }
+
+    // the saved scope still refers to the same flags, so they can be put back in place
+    pub fn restore_state(&mut self, state: ScriptState) {
+        self.scope = state.scope;
+        *self.flags.lock().unwrap() = state.flags;
+    }
+
+    pub fn wall_jump_flag(&self) -> bool {
+        self.flags.lock().unwrap().wall_jump
+    }
+
+    // for quicksaves, as:
+    //   var <name> <value>
+    //   flags <win> <wall jump>
+    //   after <ticks> <function> (followed by curry <value> for each value it captured)
+    // popups are left out since they're always shown by the end of the tick
+    pub fn to_lines(&self) -> Result<Vec<String>, String> {
+        let mut lines = Vec::new();
+        // by name, since the loader adds them in no particular order
+        let mut vars: Vec<_> = self.scope.iter().collect();
+        vars.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, _, value) in vars {
+            lines.push(format!("var {} {}", name, value_to_text(&value)?));
+        }
+        let flags = self.flags.lock().unwrap();
+        lines.push(format!("flags {} {}", flags.win, flags.wall_jump));
+        for (n, name, curry) in &flags.queued_funcs {
+            lines.push(format!("after {} {}", n, name));
+            for value in curry {
+                lines.push(format!("curry {}", value_to_text(value)?));
+            }
+        }
+        Ok(lines)
+    }
+
+    // returns false if the line was about something else
+    pub fn parse_line(&mut self, keyword: &str, f: &mut Fields) -> Result<bool, String> {
+        match keyword {
+            "var" => {
+                let name = f.word()?;
+                if !self.scope.contains(name) {
+                    return Err(format!("script has no variable {}", name));
+                }
+                let value = self.parse_value(f)?;
+                self.scope.set_value(name, value);
+            }
+            "flags" => {
+                let mut flags = self.flags.lock().unwrap();
+                flags.win = f.value()?;
+                flags.wall_jump = f.value()?;
+                flags.queued_funcs.clear();
+            }
+            "after" => {
+                let n = f.value()?;
+                let name = f.word()?;
+                let ast = self.ast.as_ref().unwrap();
+                if !ast.iter_functions().any(|func| func.name == name) {
+                    return Err(format!("script has no function {}", name));
+                }
+                let mut flags = self.flags.lock().unwrap();
+                flags.queued_funcs.push((n, name.to_owned(), Vec::new()));
+            }
+            "curry" => {
+                let value = self.parse_value(f)?;
+                match self.flags.lock().unwrap().queued_funcs.last_mut() {
+                    Some((_, _, curry)) => curry.push(value),
+                    None => return Err("curry before any function".to_owned()),
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn parse_value(&self, f: &mut Fields) -> Result<Dynamic, String> {
+        match f.word()? {
+            "entity" => {
+                let id = f.entity(&self.world_ref.lock().unwrap())?;
+                let proxy = ScriptEntityProxy::new(Arc::clone(&self.world_ref), id);
+                Ok(Dynamic::from(proxy))
+            }
+            "path" => {
+                let mut points: Vec<(f32, f32)> = Vec::new();
+                while !f.is_empty() {
+                    points.push((f.value()?, f.value()?));
+                }
+                Ok(Dynamic::from(Arc::new(points)))
+            }
+            "motion" => Ok(Dynamic::from(parse_motion(f)?)),
+            "context" => Ok(Dynamic::from(Arc::clone(&self.flags))),
+            "int" => Ok(Dynamic::from(f.value::<rhai::INT>()?)),
+            "float" => Ok(Dynamic::from(f.value::<rhai::FLOAT>()?)),
+            "bool" => Ok(Dynamic::from(f.value::<bool>()?)),
+            other => Err(format!("unknown script value {}", other)),
+        }
+    }
+}
+
+// only the kinds of value that level scripts keep in variables and capture in closures
+fn value_to_text(value: &Dynamic) -> Result<String, String> {
+    let value = value.flatten_clone();
+    if value.is::<ScriptEntityProxy>() {
+        let proxy = value.clone_cast::<ScriptEntityProxy>();
+        Ok(format!("entity {}", proxy.id.to_bits()))
+    } else if value.is::<script_interface::Path>() {
+        let mut text = "path".to_owned();
+        for (x, y) in value.clone_cast::<script_interface::Path>().iter() {
+            text.push_str(&format!(" {} {}", x, y));
+        }
+        Ok(text)
+    } else if value.is::<PathMotionType>() {
+        let motion = value.clone_cast::<PathMotionType>();
+        Ok(format!("motion {}", motion_fields(&motion)))
+    } else if value.is::<script_interface::Flags>() {
+        Ok("context".to_owned())
+    } else if value.is::<rhai::INT>() {
+        Ok(format!("int {}", value.clone_cast::<rhai::INT>()))
+    } else if value.is::<rhai::FLOAT>() {
+        Ok(format!("float {}", value.clone_cast::<rhai::FLOAT>()))
+    } else if value.is::<bool>() {
+        Ok(format!("bool {}", value.clone_cast::<bool>()))
+    } else {
+        Err(format!("can't save a script {}", value.type_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_state() {
+        let world_ref = Arc::new(Mutex::new(World::new()));
+        let mut se = ScriptEngine::new(world_ref, Default::default(), Default::default());
+        let script = "fn change() { forward_cycle = forward_once; context.win(); }";
+        se.ast = Some(se.engine.compile(script).unwrap());
+        let state = se.save_state();
+        se.call_entry_point("change");
+        assert!(se.win_flag());
+        assert!(
+            se.scope.get_value::<PathMotionType>("forward_cycle")
+                == Some(PathMotionType::ForwardOnce)
+        );
+        se.restore_state(state);
+        assert!(!se.win_flag());
+        assert!(
+            se.scope.get_value::<PathMotionType>("forward_cycle")
+                == Some(PathMotionType::ForwardCycle)
+        );
+        // the context in the restored scope is still the one the engine reads from
+        se.call_entry_point("change");
+        assert!(se.win_flag());
+    }
 }
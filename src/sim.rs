@@ -0,0 +1,143 @@
+use crate::beam::Beam;
+use crate::camera::PlayerCamera;
+use crate::checkpoint::{can_respawn, respawn, Checkpoint};
+use crate::demo::Checksum;
+use crate::enemy::update_enemies;
+use crate::explosion::Blast;
+use crate::files::AssetSource;
+use crate::input::Input;
+use crate::level::LevelInfo;
+use crate::liquid::Liquid;
+use crate::loader::{load_level_resources, LoadError};
+use crate::physics::{Actor, PathMotion};
+use crate::pickup::{Pickup, WeaponPickup};
+use crate::player::Controller;
+use crate::profile::{Phase, Profiler};
+use crate::projectile::Projectile;
+use crate::resources::{Inventory, SceneResources};
+use crate::vfx::update_vfx;
+use hecs::CommandBuffer;
+use std::future::Future;
+use std::num::NonZeroU8;
+use std::path::Path;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+// what the main loop should do after a tick, since changing scene happens outside the level
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StepOutcome {
+    Continue,
+    Respawned,
+    Restart,
+    Won,
+}
+
+// advance a running level by one fixed 30 FPS tick
+pub fn step(resources: &mut SceneResources, input: &Input, profiler: &mut Profiler) -> StepOutcome {
+    resources.history.record_input(input);
+    let mut buffer = CommandBuffer::new();
+    profiler.start(Phase::Motion);
+    PathMotion::apply(resources);
+    Liquid::update(resources);
+    profiler.start(Phase::Pickups);
+    Pickup::update(resources, &mut buffer);
+    WeaponPickup::update(resources);
+    profiler.start(Phase::Player);
+    Controller::update(resources, &mut buffer, input);
+    Checkpoint::update(resources, &mut buffer);
+    profiler.start(Phase::Enemies);
+    Blast::update(resources, &mut buffer);
+    Beam::update(resources, &mut buffer);
+    update_enemies(resources, &mut buffer);
+    profiler.start(Phase::Actor);
+    Actor::update(resources);
+    profiler.start(Phase::Projectile);
+    Projectile::update(resources, &mut buffer);
+    profiler.start(Phase::Vfx);
+    update_vfx(resources, &mut buffer);
+    profiler.stop();
+    buffer.run_on(&mut resources.world_ref.lock().unwrap());
+
+    PlayerCamera::update(resources);
+
+    let mut outcome = check_death(resources, input);
+
+    for t in &resources.triggers {
+        resources.script_engine.call_entry_point(t);
+    }
+    resources.triggers.clear();
+    resources.script_engine.schedule_queued_funcs();
+    for m in resources.script_engine.new_popups() {
+        resources.messages.add(m);
+    }
+
+    if resources.script_engine.win_flag() {
+        outcome = StepOutcome::Won;
+    }
+
+    resources.messages.update();
+    resources.selector.update();
+    resources.stats.frames += 1;
+    if resources.history.check_due() {
+        let check = Checksum::of(resources);
+        resources.history.record_check(check);
+    }
+    outcome
+}
+
+// once the player has been dead for a second, any key goes back to the checkpoint if there
+// is one, and otherwise asks for the level to be restarted
+fn check_death(resources: &mut SceneResources, input: &Input) -> StepOutcome {
+    let mut player_dead = true;
+    {
+        let w = resources.world_ref.lock().unwrap();
+        if let Ok(controller) = w.get::<&Controller>(resources.player_id) {
+            player_dead = controller.hp == 0;
+        };
+    };
+    if !player_dead {
+        return StepOutcome::Continue;
+    }
+    let dt = &mut resources
+        .death_timer
+        .get_or_insert(NonZeroU8::new(1).unwrap());
+    **dt = dt.saturating_add(1);
+    let n = dt.get();
+    if n == 60 {
+        let msg = if can_respawn(resources) {
+            "Press any key to go back to the checkpoint."
+        } else {
+            "Press any key to restart."
+        };
+        resources.messages.add(msg.to_owned());
+    }
+    if n > 30 && input.is_any_pressed() {
+        if respawn(resources) {
+            return StepOutcome::Respawned;
+        }
+        return StepOutcome::Restart;
+    }
+    StepOutcome::Continue
+}
+
+// load a level from a directory on disk without needing a window
+pub fn load_level_headless(
+    assets_dir: &Path,
+    info: LevelInfo,
+    inv: Inventory,
+    seed: u64,
+) -> Result<SceneResources, LoadError> {
+    let source = AssetSource::Directory(assets_dir.to_owned());
+    now_or_never(load_level_resources(source, info, inv, seed))
+}
+
+// reading from the filesystem never actually has to wait, so the loader's future
+// is always ready the first time it is polled
+pub(crate) fn now_or_never<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    match future.as_mut().poll(&mut cx) {
+        Poll::Ready(result) => result,
+        Poll::Pending => panic!("headless loading tried to wait for something"),
+    }
+}
@@ -27,8 +27,47 @@ impl LevelStats {
     }
 
     pub fn pretty_time(&self) -> String {
-        let m = self.frames / (30 * 60);
-        let s = (self.frames % (30 * 60)) as f32 / 30.0;
-        format_args!("{:02}:{:05.2}", m, s).to_string()
+        format_time(self.frames)
+    }
+}
+
+pub fn format_time(frames: u32) -> String {
+    let m = frames / (30 * 60);
+    let s = (frames % (30 * 60)) as f32 / 30.0;
+    format_args!("{:02}:{:05.2}", m, s).to_string()
+}
+
+// the other way round, accepting things like 1:30 or 00:45.50
+pub fn parse_time(text: &str) -> Option<u32> {
+    let (m, s) = text.split_once(':')?;
+    let m: u32 = m.parse().ok()?;
+    let s: f32 = s.parse().ok()?;
+    if !(0.0..60.0).contains(&s) {
+        return None;
+    }
+    Some(m * 30 * 60 + (s * 30.0).round() as u32)
+}
+
+// counts as a whole percentage, where a level with nothing to find counts as all of it found
+pub fn percent(n: u32, max: u32) -> u32 {
+    (n * 100).checked_div(max).unwrap_or(100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("1:02.5"), Some(62 * 30 + 15));
+        assert_eq!(parse_time("0:45"), Some(45 * 30));
+        assert_eq!(format_time(62 * 30 + 15), "01:02.50");
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(percent(1, 3), 33);
+        assert_eq!(percent(3, 3), 100);
+        assert_eq!(percent(0, 0), 100);
     }
 }
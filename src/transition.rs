@@ -1,6 +1,6 @@
 use crate::render::WALL_VISION_DEPTH;
+use crate::rng::Rng;
 use macroquad::prelude::*;
-use quad_rand::gen_range;
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub enum TransitionEffectType {
@@ -8,10 +8,10 @@ pub enum TransitionEffectType {
     Shatter,
 }
 
-pub fn new_transition(typ: TransitionEffectType) -> Box<dyn TransitionEffect> {
+pub fn new_transition(typ: TransitionEffectType, rng: &mut Rng) -> Box<dyn TransitionEffect> {
     match typ {
         TransitionEffectType::Open => Box::new(Open::new()),
-        TransitionEffectType::Shatter => Box::new(Shatter::new()),
+        TransitionEffectType::Shatter => Box::new(Shatter::new(rng)),
     }
 }
 
@@ -69,12 +69,12 @@ pub struct Shatter {
 }
 
 impl Shatter {
-    pub fn new() -> Self {
+    pub fn new(rng: &mut Rng) -> Self {
         let mut data = Vec::new();
         for y in 0..15 {
             let mut v = Vec::new();
             for x in 0..20 {
-                let a = gen_range(0.0, std::f32::consts::PI * 2.0);
+                let a = rng.gen_range(0.0, std::f32::consts::PI * 2.0);
                 v.push((
                     x as f32 * 16.0,
                     y as f32 * 16.0,
@@ -3,7 +3,7 @@ use std::cmp::Ordering;
 use hecs::{CommandBuffer, World};
 use macroquad::prelude::*;
 
-use crate::{physics::IntRect, resources::SceneResources};
+use crate::{physics::IntRect, resources::SceneResources, rng::Rng};
 
 const EXPLOSION_OUTER_COLOR: Color = Color {
     r: 0.1333,
@@ -26,6 +26,13 @@ const EXPLOSION_SMOKE_COLOR: Color = Color {
     a: 1.0,
 };
 
+const BEAM_OUTER_COLOR: Color = Color {
+    r: 1.0,
+    g: 0.2,
+    b: 0.6,
+    a: 0.75,
+};
+
 pub struct ZapFlash {
     pub x: i32,
     pub y: i32,
@@ -42,6 +49,18 @@ impl ZapFlash {
     }
 }
 
+pub struct BeamEffect {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub n: u32,
+}
+
+impl BeamEffect {
+    pub fn new(from: Vec2, to: Vec2) -> Self {
+        Self { from, to, n: 0 }
+    }
+}
+
 pub struct Explosion {
     pub x: i32,
     pub y: i32,
@@ -67,14 +86,14 @@ pub struct FireParticle {
 }
 
 impl FireParticle {
-    pub fn new_from_centre(x: i32, y: i32, a: f32) -> Self {
-        let d = quad_rand::gen_range(0.0, 4.0);
+    pub fn new_from_centre(rng: &mut Rng, x: i32, y: i32, a: f32) -> Self {
+        let d = rng.gen_range(0.0, 4.0);
         Self {
             x: x as f32 + a.cos() * d,
             y: y as f32 + a.sin() * d,
             vx: a.cos() * 4.0,
             vy: a.sin() * 4.0,
-            r: quad_rand::gen_range(8.0, 16.0),
+            r: rng.gen_range(8.0, 16.0),
         }
     }
 }
@@ -88,25 +107,25 @@ pub struct SmokeParticle {
 }
 
 impl SmokeParticle {
-    pub fn new_from_centre(x: i32, y: i32, a: f32, r: f32) -> Self {
-        let d = quad_rand::gen_range(0.0, 6.0);
+    pub fn new_from_centre(rng: &mut Rng, x: i32, y: i32, a: f32, r: f32) -> Self {
+        let d = rng.gen_range(0.0, 6.0);
         Self {
             x: x as f32 + a.cos() * d,
             y: y as f32 + a.sin() * d,
             vx: a.cos() * 1.0,
             vy: a.sin() * 1.0,
-            r: quad_rand::gen_range(r / 2.0, r),
+            r: rng.gen_range(r / 2.0, r),
         }
     }
 }
 
-pub fn create_explosion(buffer: &mut CommandBuffer, x: i32, y: i32) {
-    let mut a = quad_rand::gen_range(0.0, std::f32::consts::TAU);
+pub fn create_explosion(buffer: &mut CommandBuffer, rng: &mut Rng, x: i32, y: i32) {
+    let mut a = rng.gen_range(0.0, std::f32::consts::TAU);
     buffer.spawn((Explosion::new_from_centre(x, y),));
     a += std::f32::consts::TAU / std::f32::consts::E;
     for _ in 0..6 {
-        buffer.spawn((FireParticle::new_from_centre(x, y, a),));
-        buffer.spawn((SmokeParticle::new_from_centre(x, y, a, 16.0),));
+        buffer.spawn((FireParticle::new_from_centre(rng, x, y, a),));
+        buffer.spawn((SmokeParticle::new_from_centre(rng, x, y, a, 16.0),));
         a += std::f32::consts::TAU / std::f32::consts::E;
     }
 }
@@ -122,14 +141,21 @@ impl FireballEffect {
     }
 }
 
-pub fn update_vfx(resources: &SceneResources, buffer: &mut CommandBuffer) {
+pub fn update_vfx(resources: &mut SceneResources, buffer: &mut CommandBuffer) {
     let world = resources.world_ref.lock().unwrap();
+    let rng = &mut resources.cosmetic_rng;
     for (id, z) in world.query::<&mut ZapFlash>().iter() {
         z.n += 1;
         if z.n > 5 {
             buffer.despawn(id);
         }
     }
+    for (id, b) in world.query::<&mut BeamEffect>().iter() {
+        b.n += 1;
+        if b.n > 5 {
+            buffer.despawn(id);
+        }
+    }
     for (id, z) in world.query::<&mut Explosion>().iter() {
         z.n += 1;
         if z.n > 6 {
@@ -147,8 +173,8 @@ pub fn update_vfx(resources: &SceneResources, buffer: &mut CommandBuffer) {
     for (id, f) in world.query::<&mut SmokeParticle>().iter() {
         f.x += f.vx;
         f.y += f.vy;
-        f.vx += quad_rand::gen_range(-0.1, 0.1);
-        f.vy += quad_rand::gen_range(-0.15, 0.05);
+        f.vx += rng.gen_range(-0.1, 0.1);
+        f.vy += rng.gen_range(-0.15, 0.05);
         f.r *= 0.875;
         if f.r < 1.0 {
             buffer.despawn(id);
@@ -157,8 +183,9 @@ pub fn update_vfx(resources: &SceneResources, buffer: &mut CommandBuffer) {
     for (_id, (rect, f)) in world.query::<(&IntRect, &mut FireballEffect)>().iter() {
         f.t += 0.25;
         let c = rect.centre();
-        let a = quad_rand::gen_range(0.0, std::f32::consts::TAU);
+        let a = rng.gen_range(0.0, std::f32::consts::TAU);
         buffer.spawn((SmokeParticle::new_from_centre(
+            rng,
             c.x as i32,
             c.y as i32,
             a,
@@ -184,6 +211,20 @@ pub fn draw_vfx(world: &World) {
         draw_circle(fp.x, fp.y, fp.r * 0.75, EXPLOSION_INNER_COLOR);
     }
 
+    for (_, b) in world.query::<&BeamEffect>().iter() {
+        // starts out thick and thins away to nothing
+        let w = 3.0 - b.n as f32 * 0.5;
+        draw_line(
+            b.from.x,
+            b.from.y,
+            b.to.x,
+            b.to.y,
+            w + 2.0,
+            BEAM_OUTER_COLOR,
+        );
+        draw_line(b.from.x, b.from.y, b.to.x, b.to.y, w, WHITE);
+    }
+
     for (_, ex) in world.query::<&Explosion>().iter() {
         match ex.n.cmp(&0) {
             Ordering::Less => (),
@@ -1,14 +1,20 @@
+use crate::beam::Beam;
 use crate::input::KeyState;
 use crate::physics::{Actor, IntRect};
-use crate::projectile::{make_player_projectile, DamageEnemies, Projectile, ProjectileDrag};
+use crate::projectile::{
+    make_charged_projectile, make_player_projectile, make_rocket, DamageEnemies, Projectile,
+    ProjectileDrag,
+};
+use crate::rng::Rng;
 use crate::vfx::{FireballEffect, SmokeParticle};
 use enum_map::EnumMap;
 use hecs::CommandBuffer;
+use macroquad::math::{vec2, Vec2};
 use std::collections::VecDeque;
 
 // eventually there will be variants whose names don't end in "...Laser"
 #[allow(clippy::enum_variant_names)]
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(enum_iterator::Sequence, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum WeaponType {
     BackupLaser,
     BurstLaser,
@@ -17,6 +23,9 @@ pub enum WeaponType {
     Shotgun,
     SuperShotgun,
     ReverseShotgun,
+    RocketLauncher,
+    ChargeLaser,
+    BeamLaser,
 }
 
 pub fn weapon_name(typ: WeaponType) -> &'static str {
@@ -28,6 +37,9 @@ pub fn weapon_name(typ: WeaponType) -> &'static str {
         WeaponType::Shotgun => "shotgun",
         WeaponType::SuperShotgun => "super shotgun",
         WeaponType::ReverseShotgun => "reverse shotgun",
+        WeaponType::RocketLauncher => "rocket launcher",
+        WeaponType::ChargeLaser => "charge laser",
+        WeaponType::BeamLaser => "beam laser",
     }
 }
 
@@ -40,6 +52,9 @@ pub fn weapon_name_indef(typ: WeaponType) -> &'static str {
         WeaponType::Shotgun => "a shotgun",
         WeaponType::SuperShotgun => "a super shotgun",
         WeaponType::ReverseShotgun => "the reverse shotgun",
+        WeaponType::RocketLauncher => "a rocket launcher",
+        WeaponType::ChargeLaser => "a charge laser",
+        WeaponType::BeamLaser => "a beam laser",
     }
 }
 
@@ -52,6 +67,9 @@ pub fn weapon_sprite_frame(typ: WeaponType) -> usize {
         WeaponType::Shotgun => 3,
         WeaponType::SuperShotgun => 4,
         WeaponType::ReverseShotgun => 5,
+        WeaponType::RocketLauncher => 6,
+        WeaponType::ChargeLaser => 8,
+        WeaponType::BeamLaser => 9,
     }
 }
 
@@ -64,6 +82,9 @@ pub fn weapon_v_offset(typ: WeaponType) -> f32 {
         WeaponType::Shotgun => 4.0,
         WeaponType::SuperShotgun => 3.0,
         WeaponType::ReverseShotgun => 1.0,
+        WeaponType::RocketLauncher => 3.0,
+        WeaponType::ChargeLaser => 2.0,
+        WeaponType::BeamLaser => 3.0,
     }
 }
 
@@ -107,18 +128,82 @@ pub fn ammo_max(typ: AmmoType) -> AmmoQuantity {
     }
 }
 
+// which way the player is pointing: dx is either 0 or the way they face, and dy is -1 for up,
+// 0 for straight ahead or 1 for down
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Aim {
+    pub facing: i8,
+    pub dx: i8,
+    pub dy: i8,
+}
+
+impl Aim {
+    pub fn forward(facing: i8) -> Self {
+        Self {
+            facing,
+            dx: facing,
+            dy: 0,
+        }
+    }
+
+    pub fn dir(&self) -> Vec2 {
+        vec2(self.dx as f32, self.dy as f32).normalize()
+    }
+
+    // across the direction of fire, pointing down when firing forward
+    pub fn side(&self) -> Vec2 {
+        let d = self.dir();
+        vec2(-d.y, d.x) * self.facing as f32
+    }
+
+    pub fn reversed(&self) -> Self {
+        Self {
+            facing: -self.facing,
+            dx: -self.dx,
+            dy: -self.dy,
+        }
+    }
+
+    // the middle of where shots leave the player
+    pub fn muzzle(&self, player_rect: &IntRect) -> (i32, i32) {
+        (
+            player_rect.x + 7 + self.dx as i32 * 9,
+            player_rect.y + 13 + self.dy as i32 * 12,
+        )
+    }
+
+    // a shot centred on a point, lying along the direction of fire
+    pub fn shot_rect(&self, (x, y): (i32, i32), length: i32, thickness: i32) -> IntRect {
+        let (w, h) = match (self.dx, self.dy) {
+            (_, 0) => (length, thickness),
+            (0, _) => (thickness, length),
+            _ => ((length + thickness) / 2, (length + thickness) / 2),
+        };
+        IntRect::new(x - w / 2, y - h / 2, w, h)
+    }
+}
+
 pub trait Weapon {
     fn get_type(&self) -> WeaponType;
     fn get_ammo_type(&self) -> AmmoType;
     fn get_ammo_use(&self) -> AmmoQuantity;
+    #[allow(clippy::too_many_arguments)]
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        rng: &mut Rng,
+        cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool;
+    // from 0.0 to 1.0, for weapons that need fire to be held down for a while
+    fn charge(&self) -> f32 {
+        0.0
+    }
+    // called when another weapon is about to take this one's place at the front
+    fn deselect(&mut self) {}
 }
 
 struct BackupLaser {}
@@ -142,16 +227,17 @@ impl Weapon for BackupLaser {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if key_state == KeyState::Pressed {
-            let new_x = player_rect.x + 3 + facing as i32 * 9;
-            let rect = IntRect::new(new_x, player_rect.y + 11, 8, 5);
-            make_player_projectile(buffer, rect, facing as f32 * 10.0);
-            player.vx -= facing as f32 * 10.0;
+            let rect = aim.shot_rect(aim.muzzle(player_rect), 8, 5);
+            make_player_projectile(buffer, rect, aim.dir() * 10.0);
+            player.vx -= aim.dir().x * 10.0;
             return true;
         }
         false
@@ -160,36 +246,51 @@ impl Weapon for BackupLaser {
 
 fn make_shotgun_spray(
     buffer: &mut CommandBuffer,
-    x: i32,
-    y: i32,
-    facing: i8,
+    rng: &mut Rng,
+    (x, y): (i32, i32),
+    aim: Aim,
     n: usize,
     spread: f32,
 ) {
-    let rect = IntRect::new(x + facing as i32 * 9, y, 5, 5);
-    let vx = facing as f32 * 15.0;
+    let rect = aim.shot_rect((x, y), 5, 5);
+    let v = aim.dir() * 15.0;
     for i in 0..n {
         let c = rect.clone();
-        let proj = Projectile::new(
-            &c,
-            vx * quad_rand::gen_range(0.1, 1.0),
-            ((i as f32 / (n - 1) as f32) - 0.5) * spread * quad_rand::gen_range(0.8, 1.2),
-        );
+        let speed = rng.gen_range(0.1, 1.0);
+        let offset = ((i as f32 / (n - 1) as f32) - 0.5) * spread * rng.gen_range(0.8, 1.2);
+        let pv = v * speed + aim.side() * offset;
+        let proj = Projectile::new(&c, pv.x, pv.y);
         buffer.spawn((
             c,
             FireballEffect::new(3.0),
             proj,
             DamageEnemies {},
-            ProjectileDrag {},
+            ProjectileDrag { aimed: aim.dy != 0 },
         ));
     }
-    for _ in 0..(n / 2) {
-        buffer.spawn((SmokeParticle::new_from_centre(
-            x + 2,
-            y + 2,
-            std::f32::consts::PI / -2.0 + quad_rand::gen_range(-0.3, 0.3),
-            4.0,
-        ),));
+}
+
+fn make_shotgun_smoke(
+    buffer: &mut CommandBuffer,
+    cosmetic_rng: &mut Rng,
+    (x, y): (i32, i32),
+    n: usize,
+) {
+    for _ in 0..n {
+        let a = std::f32::consts::PI / -2.0 + cosmetic_rng.gen_range(-0.3, 0.3);
+        buffer.spawn((SmokeParticle::new_from_centre(cosmetic_rng, x, y, a, 4.0),));
+    }
+}
+
+// where the pellets start and where the smoke comes from; firing straight ahead, the smoke stays
+// by the gun and the pellets start a little way in front of it
+fn shotgun_muzzle(player_rect: &IntRect, aim: Aim) -> ((i32, i32), (i32, i32)) {
+    if aim.dy == 0 {
+        let (x, y) = (player_rect.x + 5, player_rect.y + 13);
+        ((x + aim.dx as i32 * 9, y), (x, y))
+    } else {
+        let muzzle = aim.muzzle(player_rect);
+        (muzzle, muzzle)
     }
 }
 
@@ -214,21 +315,18 @@ impl Weapon for Shotgun {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        rng: &mut Rng,
+        cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if key_state == KeyState::Pressed {
-            make_shotgun_spray(
-                buffer,
-                player_rect.x + 3,
-                player_rect.y + 11,
-                facing,
-                7,
-                5.0,
-            );
-            player.vx -= facing as f32 * 10.0;
+            let (origin, smoke) = shotgun_muzzle(player_rect, aim);
+            make_shotgun_spray(buffer, rng, origin, aim, 7, 5.0);
+            make_shotgun_smoke(buffer, cosmetic_rng, smoke, 3);
+            player.vx -= aim.dir().x * 10.0;
             return true;
         }
         false
@@ -255,21 +353,18 @@ impl Weapon for SuperShotgun {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        rng: &mut Rng,
+        cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if key_state == KeyState::Pressed {
-            make_shotgun_spray(
-                buffer,
-                player_rect.x + 3,
-                player_rect.y + 11,
-                facing,
-                15,
-                10.0,
-            );
-            player.vx -= facing as f32 * 20.0;
+            let (origin, smoke) = shotgun_muzzle(player_rect, aim);
+            make_shotgun_spray(buffer, rng, origin, aim, 15, 10.0);
+            make_shotgun_smoke(buffer, cosmetic_rng, smoke, 7);
+            player.vx -= aim.dir().x * 20.0;
             return true;
         }
         false
@@ -297,21 +392,27 @@ impl Weapon for ReverseShotgun {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        rng: &mut Rng,
+        cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if key_state == KeyState::Pressed {
-            make_shotgun_spray(
-                buffer,
-                player_rect.x + 3 + facing as i32 * 20,
-                player_rect.y + 11,
-                -facing,
-                7,
-                5.0,
-            );
-            player.vx += facing as f32 * 10.0;
+            // fired from out in front, back past the player
+            let (origin, smoke) = if aim.dy == 0 {
+                let (x, y) = (player_rect.x + 5 + aim.dx as i32 * 20, player_rect.y + 13);
+                ((x - aim.dx as i32 * 9, y), (x, y))
+            } else {
+                let (mx, my) = aim.muzzle(player_rect);
+                let d = aim.dir() * 4.0;
+                let origin = (mx + d.x as i32, my + d.y as i32);
+                (origin, origin)
+            };
+            make_shotgun_spray(buffer, rng, origin, aim.reversed(), 7, 5.0);
+            make_shotgun_smoke(buffer, cosmetic_rng, smoke, 3);
+            player.vx += aim.dir().x * 10.0;
             return true;
         }
         false
@@ -341,19 +442,20 @@ impl Weapon for AutoLaser {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if self.delay > 0 {
             self.delay -= 1
         }
-        if key_state != KeyState::NotPressed && self.delay == 0 {
-            let new_x = player_rect.x + 3 + facing as i32 * 9;
-            let rect = IntRect::new(new_x, player_rect.y + 11, 8, 5);
-            make_player_projectile(buffer, rect, facing as f32 * 10.0);
-            player.vx -= facing as f32 * 10.0;
+        if key_state.is_down() && self.delay == 0 {
+            let rect = aim.shot_rect(aim.muzzle(player_rect), 8, 5);
+            make_player_projectile(buffer, rect, aim.dir() * 10.0);
+            player.vx -= aim.dir().x * 10.0;
             self.delay = 3;
             return true;
         }
@@ -385,24 +487,25 @@ impl Weapon for BurstLaser {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if self.delay > 0 {
             self.delay -= 1
         }
-        if key_state != KeyState::NotPressed && self.delay == 0 && self.shots < 3 {
-            let new_x = player_rect.x + 3 + facing as i32 * 9;
-            let rect = IntRect::new(new_x, player_rect.y + 11, 8, 5);
-            make_player_projectile(buffer, rect, facing as f32 * 10.0);
-            player.vx -= facing as f32 * 10.0;
+        if key_state.is_down() && self.delay == 0 && self.shots < 3 {
+            let rect = aim.shot_rect(aim.muzzle(player_rect), 8, 5);
+            make_player_projectile(buffer, rect, aim.dir() * 10.0);
+            player.vx -= aim.dir().x * 10.0;
             self.delay = 2;
             self.shots += 1;
             return true;
         }
-        if key_state == KeyState::NotPressed {
+        if !key_state.is_down() {
             self.shots = 0;
         }
         false
@@ -430,18 +533,191 @@ impl Weapon for DoubleLaser {
     fn update(
         &mut self,
         buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
         player: &mut Actor,
         player_rect: &IntRect,
-        facing: i8,
+        aim: Aim,
         key_state: KeyState,
     ) -> bool {
         if key_state == KeyState::Pressed {
-            let new_x = player_rect.x + 3 + facing as i32 * 9;
-            let rect = IntRect::new(new_x, player_rect.y + 8, 8, 5);
-            make_player_projectile(buffer, rect, facing as f32 * 10.0);
-            let rect = IntRect::new(new_x, player_rect.y + 14, 8, 5);
-            make_player_projectile(buffer, rect, facing as f32 * 10.0);
-            player.vx -= facing as f32 * 10.0;
+            // the two shots are side by side across the direction of fire
+            let (mx, my) = aim.muzzle(player_rect);
+            let side = aim.side() * 3.0;
+            for s in [-1.0, 1.0] {
+                let centre = (mx + (side.x * s) as i32, my + (side.y * s) as i32);
+                make_player_projectile(buffer, aim.shot_rect(centre, 8, 5), aim.dir() * 10.0);
+            }
+            player.vx -= aim.dir().x * 10.0;
+            return true;
+        }
+        false
+    }
+}
+
+struct RocketLauncher {
+    delay: u8,
+}
+
+impl RocketLauncher {
+    fn new() -> Self {
+        Self { delay: 0 }
+    }
+}
+
+impl Weapon for RocketLauncher {
+    fn get_type(&self) -> WeaponType {
+        WeaponType::RocketLauncher
+    }
+    fn get_ammo_type(&self) -> AmmoType {
+        AmmoType::Rocket
+    }
+    fn get_ammo_use(&self) -> AmmoQuantity {
+        1
+    }
+    fn update(
+        &mut self,
+        buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
+        player: &mut Actor,
+        player_rect: &IntRect,
+        aim: Aim,
+        key_state: KeyState,
+    ) -> bool {
+        if self.delay > 0 {
+            self.delay -= 1
+        }
+        if key_state == KeyState::Pressed && self.delay == 0 {
+            let rect = aim.shot_rect(aim.muzzle(player_rect), 8, 6);
+            make_rocket(buffer, rect, aim.dir() * 4.0);
+            player.vx -= aim.dir().x * 15.0;
+            self.delay = 15;
+            return true;
+        }
+        false
+    }
+}
+
+const FULL_CHARGE: u8 = 30;
+// the least charge that makes a shot at all
+const MIN_CHARGE: u8 = FULL_CHARGE / 3;
+// how strong a shot is at full charge, going down to 1 at the least
+const MAX_CHARGE_POWER: u8 = 3;
+
+struct ChargeLaser {
+    charge: u8,
+    charging: bool,
+}
+
+impl ChargeLaser {
+    fn new() -> Self {
+        Self {
+            charge: 0,
+            charging: false,
+        }
+    }
+}
+
+impl Weapon for ChargeLaser {
+    fn get_type(&self) -> WeaponType {
+        WeaponType::ChargeLaser
+    }
+    fn get_ammo_type(&self) -> AmmoType {
+        AmmoType::Cell
+    }
+    fn get_ammo_use(&self) -> AmmoQuantity {
+        2
+    }
+    fn update(
+        &mut self,
+        buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
+        player: &mut Actor,
+        player_rect: &IntRect,
+        aim: Aim,
+        key_state: KeyState,
+    ) -> bool {
+        match key_state {
+            KeyState::Pressed => {
+                self.charging = true;
+                self.charge = 0;
+                false
+            }
+            KeyState::Held => {
+                if self.charging && self.charge < FULL_CHARGE {
+                    self.charge += 1;
+                }
+                false
+            }
+            // fire may have been let go while the player couldn't shoot, so don't rely on
+            // seeing Released
+            KeyState::Released | KeyState::NotPressed if self.charging => {
+                let charge = self.charge;
+                self.deselect();
+                // letting go too early fizzles out rather than costing a shot's ammo
+                if charge < MIN_CHARGE {
+                    return false;
+                }
+                let power =
+                    1 + (MAX_CHARGE_POWER - 1) * (charge - MIN_CHARGE) / (FULL_CHARGE - MIN_CHARGE);
+                let rect = aim.shot_rect(aim.muzzle(player_rect), 12, 9);
+                make_charged_projectile(buffer, rect, aim.dir() * 12.0, power);
+                player.vx -= aim.dir().x * 20.0;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn charge(&self) -> f32 {
+        self.charge as f32 / FULL_CHARGE as f32
+    }
+    fn deselect(&mut self) {
+        self.charging = false;
+        self.charge = 0;
+    }
+}
+
+struct BeamLaser {
+    delay: u8,
+}
+
+impl BeamLaser {
+    fn new() -> Self {
+        Self { delay: 0 }
+    }
+}
+
+impl Weapon for BeamLaser {
+    fn get_type(&self) -> WeaponType {
+        WeaponType::BeamLaser
+    }
+    fn get_ammo_type(&self) -> AmmoType {
+        AmmoType::Cell
+    }
+    fn get_ammo_use(&self) -> AmmoQuantity {
+        1
+    }
+    fn update(
+        &mut self,
+        buffer: &mut CommandBuffer,
+        _rng: &mut Rng,
+        _cosmetic_rng: &mut Rng,
+        player: &mut Actor,
+        player_rect: &IntRect,
+        aim: Aim,
+        key_state: KeyState,
+    ) -> bool {
+        if self.delay > 0 {
+            self.delay -= 1
+        }
+        if key_state == KeyState::Pressed && self.delay == 0 {
+            let (x, y) = aim.muzzle(player_rect);
+            let beam = Beam::new(vec2(x as f32, y as f32), aim.dir(), 240.0, 2);
+            buffer.spawn((beam,));
+            player.vx -= aim.dir().x * 5.0;
+            self.delay = 20;
             return true;
         }
         false
@@ -457,6 +733,9 @@ pub fn new_weapon(typ: WeaponType) -> Box<dyn Weapon> {
         WeaponType::Shotgun => Box::new(Shotgun::new()),
         WeaponType::SuperShotgun => Box::new(SuperShotgun::new()),
         WeaponType::ReverseShotgun => Box::new(ReverseShotgun::new()),
+        WeaponType::RocketLauncher => Box::new(RocketLauncher::new()),
+        WeaponType::ChargeLaser => Box::new(ChargeLaser::new()),
+        WeaponType::BeamLaser => Box::new(BeamLaser::new()),
     }
 }
 
@@ -531,6 +810,7 @@ pub fn select_fireable_weapon(
                 // visual as it wasn't selected a moment ago
                 selector.change(-1.0);
             } else {
+                weapons[0].deselect();
                 weapons.rotate_left(idx);
                 selector.change(-(idx as f32));
             }
@@ -538,6 +818,9 @@ pub fn select_fireable_weapon(
         }
     }
     // if we couldn't find anything, add a backup laser to inventory
+    if let Some(w) = weapons.front_mut() {
+        w.deselect();
+    }
     weapons.push_front(new_weapon(WeaponType::BackupLaser));
     selector.change(-1.0);
 }
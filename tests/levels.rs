@@ -0,0 +1,844 @@
+use macroquad::math::vec2;
+use princess_robot::checkpoint::respawn;
+use princess_robot::demo::{Demo, DemoPlayer, DemoRecorder};
+use princess_robot::explosion::Blast;
+use princess_robot::input::{Input, InputFrame, VirtualKey};
+use princess_robot::level::LevelInfo;
+use princess_robot::lint::check_level;
+use princess_robot::liquid::{Liquid, LiquidKind};
+use princess_robot::loader::LoadError;
+use princess_robot::physics::{raycast, IntRect, TileBody};
+use princess_robot::player::Controller;
+use princess_robot::profile::Profiler;
+use princess_robot::projectile::{Projectile, Rocket};
+use princess_robot::quicksave::{quicksave, save_quicksave, Quicksave};
+use princess_robot::resources::{Inventory, SceneResources};
+use princess_robot::save::{CampaignSave, Storage};
+use princess_robot::sim::{load_level_headless, step, StepOutcome};
+use princess_robot::switch::Switch;
+use princess_robot::vfx::{BeamEffect, ZapFlash};
+use princess_robot::weapon::{new_weapon, AmmoType, WeaponType};
+use std::path::{Path, PathBuf};
+
+fn assets_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")
+}
+
+fn load(path: &str, seed: u64) -> SceneResources {
+    let info = LevelInfo {
+        number: None,
+        path: path.to_owned(),
+        name: path.to_owned(),
+        par: None,
+    };
+    load_level_headless(&assets_dir(), info, Inventory::new(), seed).unwrap()
+}
+
+// turn a list of (ticks, keys held) into the input for each tick,
+// with a key counting as pressed on the first tick it is held
+fn frames(script: &[(u32, &[VirtualKey])]) -> Vec<InputFrame> {
+    let mut result = Vec::new();
+    let mut input = Input::new();
+    let mut prev: &[VirtualKey] = &[];
+    for (ticks, keys) in script {
+        for t in 0..*ticks {
+            input.clear();
+            for vk in keys.iter() {
+                if t == 0 && !prev.contains(vk) {
+                    input.press(*vk);
+                } else {
+                    input.hold(*vk);
+                }
+            }
+            result.push(input.frame());
+            input.end_tick();
+        }
+        prev = *keys;
+    }
+    result
+}
+
+fn play(resources: &mut SceneResources, frames: &[InputFrame]) {
+    let mut input = Input::new();
+    let mut profiler = Profiler::disabled();
+    for frame in frames {
+        input.set_frame(*frame);
+        step(resources, &input, &mut profiler);
+    }
+}
+
+fn record(path: &str, seed: u64, frames: &[InputFrame]) -> Demo {
+    let mut resources = load(path, seed);
+    let mut recorder = DemoRecorder::new(path, seed, Inventory::new());
+    let mut input = Input::new();
+    let mut profiler = Profiler::disabled();
+    for frame in frames {
+        input.set_frame(*frame);
+        recorder.record_input(&input);
+        step(&mut resources, &input, &mut profiler);
+        recorder.record_state(&resources);
+    }
+    recorder.finish()
+}
+
+// player x, y and hp
+fn player_state(resources: &SceneResources) -> (i32, i32, u8) {
+    let world = resources.world_ref.lock().unwrap();
+    let rect = world.get::<&IntRect>(resources.player_id).unwrap();
+    let controller = world.get::<&Controller>(resources.player_id).unwrap();
+    (rect.x, rect.y, controller.hp)
+}
+
+// a quicksave split up at each shot, with the shots sorted since the world keeps each kind of
+// shot together in the order the kinds first turned up, which a quickload can't know about
+fn quicksave_parts(resources: &SceneResources) -> Vec<String> {
+    let text = quicksave(resources).unwrap();
+    let mut parts: Vec<String> = text
+        .trim_end()
+        .split("\nshot\n")
+        .map(str::to_owned)
+        .collect();
+    parts[1..].sort();
+    parts
+}
+
+// kills, items and secrets
+fn progress(resources: &SceneResources) -> (u32, u32, u32) {
+    let s = &resources.stats;
+    (s.kills, s.items, s.secrets)
+}
+
+const BUSY_SCRIPT: &[(u32, &[VirtualKey])] = &[
+    (20, &[VirtualKey::Right]),
+    (10, &[VirtualKey::Right, VirtualKey::Jump]),
+    (15, &[VirtualKey::Fire]),
+    (5, &[]),
+    (15, &[VirtualKey::Fire]),
+    (30, &[VirtualKey::Left]),
+    (10, &[VirtualKey::Left, VirtualKey::Jump]),
+    (20, &[VirtualKey::Interact]),
+    (100, &[]),
+    (40, &[VirtualKey::Right, VirtualKey::Fire]),
+    (100, &[]),
+];
+
+#[test]
+fn level_totals() {
+    let intro = load("intro", 0);
+    let s = &intro.stats;
+    assert_eq!((s.max_kills, s.max_items, s.max_secrets), (14, 13, 2));
+    let chasm = load("chasm", 0);
+    let s = &chasm.stats;
+    assert_eq!((s.max_kills, s.max_items, s.max_secrets), (24, 40, 3));
+}
+
+#[test]
+fn shipped_levels_pass_check() {
+    for path in [
+        "intro",
+        "chasm",
+        "test-maps/groups",
+        "test-maps/liquids",
+        "test-maps/checkpoints",
+    ] {
+        assert_eq!(
+            check_level(&assets_dir(), path).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+}
+
+#[test]
+fn empty_layer_is_skipped_and_reported() {
+    let resources = load("test-maps/empty", 0);
+    assert_eq!(resources.draw_order.len(), 1);
+    assert_eq!(
+        check_level(&assets_dir(), "test-maps/empty").unwrap(),
+        vec!["test-maps/empty.tmx: layer unused has no tiles so it won't be loaded".to_owned()]
+    );
+}
+
+#[test]
+fn tiles_from_two_tilesets() {
+    // a decoration tile from the second tileset in among the terrain from the first
+    let mut resources = load("test-maps/tilesets", 0);
+    let mut gids: Vec<u32> = {
+        let world = resources.world_ref.lock().unwrap();
+        let gids = world
+            .query::<&TileBody>()
+            .iter()
+            .flat_map(|(_, body)| body.tiles.clone())
+            .filter(|gid| *gid != 0)
+            .collect();
+        gids
+    };
+    gids.sort();
+    gids.dedup();
+    assert_eq!(gids, vec![1, 38]);
+    assert_eq!(resources.tileset_for(1).first_gid, 1);
+    assert_eq!(resources.tileset_for(36).first_gid, 1);
+    assert_eq!(resources.tileset_for(38).first_gid, 37);
+    // the decoration isn't solid, so the player can walk through it
+    play(&mut resources, &frames(&[(30, &[VirtualKey::Left])]));
+    assert_eq!(player_state(&resources).0, 16);
+}
+
+#[test]
+fn finite_and_group_layers() {
+    let resources = load("test-maps/groups", 0);
+    let world = resources.world_ref.lock().unwrap();
+    let bodies: Vec<(i32, i32, bool)> = resources
+        .draw_order
+        .iter()
+        .map(|id| {
+            let body = world.get::<&TileBody>(*id).unwrap();
+            (body.x, body.width, body.door)
+        })
+        .collect();
+    // the gates inherit door from the group they're in, even when nested another level down
+    assert_eq!(bodies, vec![(0, 8, false), (80, 1, true), (48, 1, true)]);
+}
+
+#[test]
+fn conveyors_and_hazards() {
+    // the start is on a conveyor, and further right is a hazard in front of the wall
+    let mut resources = load("test-maps/behaviours", 0);
+    play(&mut resources, &frames(&[(30, &[])]));
+    let (x, y, hp) = player_state(&resources);
+    assert!(x > 52, "conveyor only moved the player to {}", x);
+    assert_eq!((y, hp), (88, 3));
+    // walking into its side is safe, but standing on it isn't
+    play(&mut resources, &frames(&[(90, &[VirtualKey::Right])]));
+    assert_eq!(player_state(&resources).2, 3);
+    play(
+        &mut resources,
+        &frames(&[
+            (4, &[VirtualKey::Jump]),
+            (4, &[]),
+            (6, &[VirtualKey::Right]),
+            (20, &[]),
+        ]),
+    );
+    assert!(player_state(&resources).2 < 3);
+}
+
+#[test]
+fn walk_over_slopes() {
+    // two bumps made of slopes either side of a block, too high to walk into without them
+    let mut resources = load("test-maps/slopes", 0);
+    play(&mut resources, &frames(&[(120, &[VirtualKey::Right])]));
+    assert_eq!(player_state(&resources), (354, 88, 3));
+    play(&mut resources, &frames(&[(120, &[VirtualKey::Left])]));
+    assert_eq!(player_state(&resources), (16, 88, 3));
+}
+
+#[test]
+fn shoot_into_slope() {
+    let mut resources = load("test-maps/slopes", 0);
+    // along the middle of the first up slope, which is halfway up at its centre
+    let d = {
+        let world = resources.world_ref.lock().unwrap();
+        raycast(
+            &world,
+            &resources.body_index,
+            vec2(64.0, 104.0),
+            vec2(1.0, 0.0),
+            200.0,
+        )
+    };
+    assert_eq!(d, Some(72.0));
+    play(
+        &mut resources,
+        &frames(&[(1, &[VirtualKey::Fire]), (9, &[])]),
+    );
+    let world = resources.world_ref.lock().unwrap();
+    let flashes: Vec<i32> = world
+        .query::<&ZapFlash>()
+        .iter()
+        .map(|(_, z)| z.x + 4)
+        .collect();
+    // the shot stops on the slope rather than going through it to the block behind
+    assert_eq!(flashes.len(), 1);
+    assert!(flashes[0] < 144, "shot hit at {}", flashes[0]);
+}
+
+#[test]
+fn climb_ladder() {
+    // a ladder up to a ledge that is too high to jump onto
+    let mut resources = load("test-maps/ladders", 0);
+    // aiming up with fire held doesn't grab the ladder
+    let start = player_state(&resources);
+    play(
+        &mut resources,
+        &frames(&[(20, &[VirtualKey::Up, VirtualKey::Fire]), (1, &[])]),
+    );
+    assert_eq!(player_state(&resources), start);
+    play(&mut resources, &frames(&[(60, &[VirtualKey::Up])]));
+    assert_eq!(player_state(&resources), (80, 40, 3));
+    play(
+        &mut resources,
+        &frames(&[(30, &[VirtualKey::Right]), (10, &[])]),
+    );
+    assert_eq!(player_state(&resources), (162, 40, 3));
+}
+
+#[test]
+fn swim_and_acid() {
+    // a deep pool of water, a puddle of acid on the floor and a tank of acid being raised
+    let mut resources = load("test-maps/liquids", 0);
+    let swim: Vec<(u32, &[VirtualKey])> = (0..5)
+        .flat_map(|_| [(2, &[VirtualKey::Jump][..]), (4, &[][..])])
+        .collect();
+    play(&mut resources, &frames(&swim));
+    let (_, y, hp) = player_state(&resources);
+    assert!(y < 100, "only swam up to {}", y);
+    assert_eq!(hp, 3);
+    play(&mut resources, &frames(&[(60, &[VirtualKey::Right])]));
+    assert!(player_state(&resources).2 < 3);
+    let world = resources.world_ref.lock().unwrap();
+    let acid_tops: Vec<i32> = world
+        .query::<(&Liquid, &IntRect)>()
+        .iter()
+        .filter(|(_, (l, _))| l.kind == LiquidKind::Acid)
+        .map(|(_, (_, r))| r.y)
+        .collect();
+    assert!(acid_tops.contains(&0), "{:?}", acid_tops);
+}
+
+#[test]
+fn liquid_attached_to_missing_layer() {
+    // misattached gives attach as a number instead of a layer name
+    for path in ["detached", "misattached"] {
+        let info = LevelInfo {
+            number: None,
+            path: format!("test-maps/{}", path),
+            name: path.to_owned(),
+            par: None,
+        };
+        let result = load_level_headless(&assets_dir(), info, Inventory::new(), 0);
+        assert!(matches!(result, Err(LoadError::BadMap { .. })), "{}", path);
+    }
+}
+
+#[test]
+fn wall_slide_and_jump() {
+    // the player starts in the air next to a wall in a level that unlocks wall jumping
+    let mut resources = load("test-maps/walls", 0);
+    play(&mut resources, &frames(&[(20, &[VirtualKey::Right])]));
+    let (x, y, _) = player_state(&resources);
+    assert_eq!(x, 162);
+    assert!(y < 100, "slid down to {}", y);
+    play(
+        &mut resources,
+        &frames(&[(1, &[VirtualKey::Right, VirtualKey::Jump]), (10, &[])]),
+    );
+    let (x, _, _) = player_state(&resources);
+    assert!(x < 155, "only jumped to {}", x);
+}
+
+// the y position of each tile layer, in draw order
+fn body_heights(resources: &SceneResources) -> Vec<i32> {
+    let world = resources.world_ref.lock().unwrap();
+    resources
+        .draw_order
+        .iter()
+        .map(|id| world.get::<&TileBody>(*id).unwrap().y)
+        .collect()
+}
+
+#[test]
+fn respawn_at_checkpoint() {
+    // walk past a checkpoint, through a zone that raises a gate, over some shells and a wall
+    // jump and into lava
+    let mut resources = load("test-maps/checkpoints", 0);
+    assert!(!respawn(&mut resources));
+    play(&mut resources, &frames(&[(200, &[VirtualKey::Right])]));
+    assert!(resources
+        .world_ref
+        .lock()
+        .unwrap()
+        .get::<&Controller>(resources.player_id)
+        .is_err());
+    assert_eq!(body_heights(&resources), vec![0, 0]);
+    assert_eq!(resources.ammo[AmmoType::Shell], 4);
+    assert!(respawn(&mut resources));
+    assert_eq!(player_state(&resources), (64, 88, 3));
+    assert_eq!(body_heights(&resources), vec![0, 16]);
+    assert_eq!(resources.ammo[AmmoType::Shell], 4);
+}
+
+#[test]
+fn respawn_keeps_what_pickups_gave() {
+    // the shells and wall jump past the checkpoint are gone once collected, so dying can't
+    // take away what they gave
+    let mut resources = load("test-maps/checkpoints", 0);
+    play(&mut resources, &frames(&[(200, &[VirtualKey::Right])]));
+    assert_eq!(resources.stats.items, 2);
+    assert!(respawn(&mut resources));
+    assert_eq!(resources.ammo[AmmoType::Shell], 4);
+    let world = resources.world_ref.lock().unwrap();
+    let controller = world.get::<&Controller>(resources.player_id).unwrap();
+    assert!(controller.wall_jump);
+}
+
+#[test]
+fn any_key_goes_back_to_checkpoint() {
+    let mut resources = load("test-maps/checkpoints", 0);
+    play(&mut resources, &frames(&[(200, &[VirtualKey::Right])]));
+    let mut input = Input::new();
+    let mut profiler = Profiler::disabled();
+    // the player fell in the lava long enough ago for a key to count
+    input.press(VirtualKey::Jump);
+    assert_eq!(
+        step(&mut resources, &input, &mut profiler),
+        StepOutcome::Respawned
+    );
+    assert_eq!(player_state(&resources), (64, 88, 3));
+    assert!(resources.death_timer.is_none());
+    // without a checkpoint the level has to start again
+    let mut resources = load("test-maps/behaviours", 0);
+    play(
+        &mut resources,
+        &frames(&[
+            (90, &[VirtualKey::Right]),
+            (4, &[VirtualKey::Jump]),
+            (4, &[]),
+            (6, &[VirtualKey::Right]),
+            (150, &[]),
+        ]),
+    );
+    assert_eq!(
+        step(&mut resources, &input, &mut profiler),
+        StepOutcome::Restart
+    );
+}
+
+#[test]
+fn rocket_hits_wall() {
+    let mut resources = load("test-maps/weapons", 0);
+    resources.weapons = [new_weapon(WeaponType::RocketLauncher)].into();
+    resources.ammo[AmmoType::Rocket] = 2;
+    play(
+        &mut resources,
+        &frames(&[(1, &[VirtualKey::Fire]), (1, &[])]),
+    );
+    assert_eq!(resources.ammo[AmmoType::Rocket], 1);
+    let rockets = |resources: &SceneResources| {
+        let world = resources.world_ref.lock().unwrap();
+        let n = world.query::<&Rocket>().iter().count();
+        n
+    };
+    assert_eq!(rockets(&resources), 1);
+    play(&mut resources, &frames(&[(200, &[])]));
+    assert_eq!(rockets(&resources), 0);
+}
+
+#[test]
+fn blasts_are_blocked_by_walls() {
+    let mut resources = load("test-maps/weapons", 0);
+    let (cx, cy) = {
+        let world = resources.world_ref.lock().unwrap();
+        let c = world
+            .get::<&IntRect>(resources.player_id)
+            .unwrap()
+            .centre_int();
+        c
+    };
+    let add_blast = |resources: &mut SceneResources, x: i32, y: i32| {
+        let blast = Blast::new(x, y, 60.0, 1, 10.0).hurting_player();
+        resources.world_ref.lock().unwrap().spawn((blast,));
+    };
+    // the floor is between the player and this one
+    add_blast(&mut resources, cx, 72);
+    play(&mut resources, &frames(&[(2, &[])]));
+    let (x, _, hp) = player_state(&resources);
+    assert_eq!(hp, 3);
+    add_blast(&mut resources, cx + 20, cy);
+    play(&mut resources, &frames(&[(2, &[])]));
+    let (pushed_x, _, hp) = player_state(&resources);
+    assert_eq!(hp, 2);
+    assert!(pushed_x < x, "player wasn't pushed away from the blast");
+}
+
+#[test]
+fn blast_damage_falls_off_for_the_player() {
+    let hp_after_blast = |dx: i32| {
+        let mut resources = load("test-maps/weapons", 0);
+        {
+            let mut world = resources.world_ref.lock().unwrap();
+            let (cx, cy) = world
+                .get::<&IntRect>(resources.player_id)
+                .unwrap()
+                .centre_int();
+            world.spawn((Blast::new(cx + dx, cy, 60.0, 2, 0.0).hurting_player(),));
+        }
+        play(&mut resources, &frames(&[(2, &[])]));
+        player_state(&resources).2
+    };
+    assert_eq!(hp_after_blast(0), 1);
+    assert_eq!(hp_after_blast(40), 2);
+    assert_eq!(hp_after_blast(70), 3);
+}
+
+#[test]
+fn aim_up_and_diagonally() {
+    let mut resources = load("test-maps/weapons", 0);
+    let shots = |resources: &SceneResources| {
+        let world = resources.world_ref.lock().unwrap();
+        let v: Vec<(f32, f32)> = world
+            .query::<&Projectile>()
+            .iter()
+            .map(|(_, p)| (p.vx, p.vy))
+            .collect();
+        v
+    };
+    let up_fire: &[VirtualKey] = &[VirtualKey::Up, VirtualKey::Fire];
+    play(
+        &mut resources,
+        &frames(&[(1, up_fire), (1, &[VirtualKey::Up])]),
+    );
+    assert_eq!(shots(&resources), vec![(0.0, -10.0)]);
+    // wait for it to hit the ceiling
+    play(&mut resources, &frames(&[(20, &[])]));
+    assert!(shots(&resources).is_empty());
+    let diagonal: &[VirtualKey] = &[VirtualKey::Up, VirtualKey::Right];
+    let diagonal_fire: &[VirtualKey] = &[VirtualKey::Up, VirtualKey::Right, VirtualKey::Fire];
+    play(
+        &mut resources,
+        &frames(&[(1, diagonal_fire), (1, diagonal)]),
+    );
+    let shot = shots(&resources)[0];
+    assert!(shot.0 > 0.0 && shot.1 < 0.0 && (shot.0 + shot.1).abs() < 0.001);
+}
+
+#[test]
+fn charge_shot_on_release() {
+    let mut resources = load("test-maps/weapons", 0);
+    resources.weapons = [new_weapon(WeaponType::ChargeLaser)].into();
+    resources.ammo[AmmoType::Cell] = 10;
+    let shots = |resources: &SceneResources| {
+        let world = resources.world_ref.lock().unwrap();
+        let n = world.query::<&Projectile>().iter().count();
+        n
+    };
+    let hold_and_release = frames(&[(35, &[VirtualKey::Fire]), (1, &[])]);
+    play(&mut resources, &hold_and_release[..35]);
+    assert_eq!(shots(&resources), 0);
+    assert_eq!(resources.weapons[0].charge(), 1.0);
+    play(&mut resources, &hold_and_release[35..]);
+    assert_eq!(shots(&resources), 1);
+    assert_eq!(resources.ammo[AmmoType::Cell], 8);
+    assert_eq!(resources.weapons[0].charge(), 0.0);
+    // letting go early doesn't fire anything or use any ammo
+    play(
+        &mut resources,
+        &frames(&[(10, &[VirtualKey::Fire]), (1, &[])]),
+    );
+    assert_eq!(resources.ammo[AmmoType::Cell], 8);
+    assert_eq!(resources.weapons[0].charge(), 0.0);
+}
+
+#[test]
+fn longer_charge_makes_stronger_shot() {
+    // the quicksave lists each shot's damage and how many enemies it can go through
+    let piercing = |ticks: u32| {
+        let mut resources = load("test-maps/weapons", 0);
+        resources.weapons = [new_weapon(WeaponType::ChargeLaser)].into();
+        resources.ammo[AmmoType::Cell] = 10;
+        play(
+            &mut resources,
+            &frames(&[(ticks, &[VirtualKey::Fire]), (1, &[])]),
+        );
+        let text = quicksave(&resources).unwrap();
+        let line = text.lines().find(|l| l.starts_with("piercing")).unwrap();
+        line.to_owned()
+    };
+    assert_eq!(piercing(11), "piercing 1 1 0");
+    assert_eq!(piercing(21), "piercing 2 2 0");
+    assert_eq!(piercing(31), "piercing 3 3 0");
+}
+
+#[test]
+fn beam_stops_at_ceiling() {
+    let mut resources = load("test-maps/weapons", 0);
+    assert_eq!(
+        IntRect::new(10, 0, 4, 4).ray_distance(vec2(0.0, 2.0), vec2(1.0, 0.0), 100.0),
+        Some(10.0)
+    );
+    let from = {
+        let world = resources.world_ref.lock().unwrap();
+        let c = world.get::<&IntRect>(resources.player_id).unwrap().centre();
+        c
+    };
+    let up = vec2(0.0, -1.0);
+    let d = {
+        let world = resources.world_ref.lock().unwrap();
+        raycast(&world, &resources.body_index, from, up, 1000.0)
+    };
+    // the underside of the ceiling
+    assert_eq!(d, Some(from.y + 64.0));
+    resources.weapons = [new_weapon(WeaponType::BeamLaser)].into();
+    resources.ammo[AmmoType::Cell] = 5;
+    let up_fire: &[VirtualKey] = &[VirtualKey::Up, VirtualKey::Fire];
+    play(
+        &mut resources,
+        &frames(&[(1, up_fire), (1, &[VirtualKey::Up])]),
+    );
+    assert_eq!(resources.ammo[AmmoType::Cell], 4);
+    let world = resources.world_ref.lock().unwrap();
+    let ends: Vec<f32> = world
+        .query::<&BeamEffect>()
+        .iter()
+        .map(|(_, b)| b.to.y)
+        .collect();
+    assert_eq!(ends, vec![-64.0]);
+}
+
+#[test]
+fn intro_standing_still() {
+    let mut resources = load("intro", 0);
+    play(&mut resources, &frames(&[(150, &[])]));
+    assert_eq!(player_state(&resources), (-192, 56, 3));
+    assert_eq!(progress(&resources), (0, 0, 0));
+    assert_eq!(resources.stats.frames, 150);
+    assert!(!resources.script_engine.win_flag());
+    // the lights on the walls blink
+    assert!(resources.tilesets[0].animations.contains_key(&15));
+}
+
+#[test]
+fn intro_walk_into_walls() {
+    let mut resources = load("intro", 0);
+    play(&mut resources, &frames(&[(60, &[VirtualKey::Right])]));
+    assert_eq!(player_state(&resources), (-174, 56, 3));
+    play(&mut resources, &frames(&[(60, &[VirtualKey::Left])]));
+    assert_eq!(player_state(&resources), (-208, 56, 3));
+    // walking left goes over the first sign
+    assert!(resources
+        .messages
+        .iter_messages()
+        .any(|m| m == "Press Z to jump."));
+    assert!(!resources.script_engine.win_flag());
+}
+
+#[test]
+fn chasm_start_lift() {
+    let mut resources = load("chasm", 0);
+    play(&mut resources, &frames(&[(150, &[])]));
+    // the lift the player starts on carries them up seven tiles
+    assert_eq!(player_state(&resources), (-696, 424, 3));
+    assert_eq!(progress(&resources), (0, 0, 0));
+    assert!(!resources.script_engine.win_flag());
+}
+
+#[test]
+fn same_seed_same_run() {
+    let frames = frames(BUSY_SCRIPT);
+    for path in ["intro", "chasm"] {
+        let first = record(path, 1234, &frames);
+        let second = record(path, 1234, &frames);
+        assert_eq!(first.to_text(), second.to_text());
+    }
+}
+
+#[test]
+fn quickload_restores_quicksave() {
+    // leaving out the last wait, which the player doesn't survive in the chasm
+    let (before, after) = BUSY_SCRIPT[..10].split_at(7);
+    for path in ["intro", "chasm"] {
+        let mut resources = load(path, 5);
+        play(&mut resources, &frames(before));
+        let text = quicksave(&resources).unwrap();
+        let save = Quicksave::parse(&text).unwrap();
+        let mut loaded = load(&save.level, save.seed);
+        save.apply(&mut loaded).unwrap();
+        assert_eq!(quicksave(&loaded).unwrap(), text);
+        // and both carry on the same way
+        play(&mut resources, &frames(after));
+        play(&mut loaded, &frames(after));
+        assert_eq!(quicksave_parts(&loaded), quicksave_parts(&resources));
+    }
+}
+
+#[test]
+fn quicksave_after_respawn() {
+    let mut resources = load("test-maps/checkpoints", 0);
+    // die in the lava, then press a key to go back to the checkpoint
+    play(
+        &mut resources,
+        &frames(&[
+            (200, &[VirtualKey::Right]),
+            (1, &[VirtualKey::Jump]),
+            (20, &[VirtualKey::Right]),
+        ]),
+    );
+    let save = Quicksave::parse(&quicksave(&resources).unwrap()).unwrap();
+    let mut loaded = load(&save.level, save.seed);
+    save.apply(&mut loaded).unwrap();
+    assert_eq!(player_state(&loaded), player_state(&resources));
+    assert_eq!(body_heights(&loaded), body_heights(&resources));
+    assert!(loaded.checkpoint.is_some());
+}
+
+#[test]
+fn no_quicksave_while_dead() {
+    let mut resources = load("test-maps/checkpoints", 0);
+    play(&mut resources, &frames(&[(200, &[VirtualKey::Right])]));
+    assert!(resources.death_timer.is_some());
+    assert!(quicksave(&resources).is_err());
+}
+
+#[test]
+fn quicksave_with_shots_in_flight() {
+    let mut resources = load("test-maps/weapons", 0);
+    resources.weapons = [new_weapon(WeaponType::ChargeLaser)].into();
+    resources.ammo[AmmoType::Cell] = 10;
+    play(
+        &mut resources,
+        &frames(&[(35, &[VirtualKey::Fire]), (2, &[])]),
+    );
+    let blast = Blast::new(0, 0, 8.0, 1, 1.0).hurting_player();
+    resources.world_ref.lock().unwrap().spawn((blast,));
+    let shots = |resources: &SceneResources| {
+        let world = resources.world_ref.lock().unwrap();
+        let n =
+            world.query::<&Projectile>().iter().count() + world.query::<&Blast>().iter().count();
+        n
+    };
+    assert_eq!(shots(&resources), 2);
+    let text = quicksave(&resources).unwrap();
+    let save = Quicksave::parse(&text).unwrap();
+    let mut loaded = load(&save.level, save.seed);
+    save.apply(&mut loaded).unwrap();
+    assert_eq!(shots(&loaded), 2);
+    assert_eq!(quicksave(&loaded).unwrap(), text);
+    play(&mut resources, &frames(&[(10, &[])]));
+    play(&mut loaded, &frames(&[(10, &[])]));
+    assert_eq!(quicksave_parts(&loaded), quicksave_parts(&resources));
+}
+
+#[test]
+fn quickload_from_storage() {
+    let mut storage = Storage::Memory(Default::default());
+    assert!(Quicksave::load(&storage).unwrap().is_none());
+    let mut resources = load("intro", 0);
+    resources.ammo[AmmoType::Shell] = 5;
+    play(&mut resources, &frames(&[(10, &[VirtualKey::Right])]));
+    assert_eq!(resources.ammo[AmmoType::Shell], 5);
+    save_quicksave(&resources, &mut storage).unwrap();
+    let save = Quicksave::load(&storage).unwrap().unwrap();
+    let mut loaded = load(&save.level, save.seed);
+    save.apply(&mut loaded).unwrap();
+    assert_eq!(player_state(&loaded), player_state(&resources));
+    assert_eq!(loaded.ammo[AmmoType::Shell], 5);
+}
+
+#[test]
+fn quickload_into_wrong_level() {
+    let resources = load("intro", 0);
+    let save = Quicksave::parse(&quicksave(&resources).unwrap()).unwrap();
+    let mut other = load("chasm", 0);
+    assert!(save.apply(&mut other).is_err());
+}
+
+#[test]
+fn campaign_save_round_trip() {
+    let mut storage = Storage::Memory(Default::default());
+    assert!(CampaignSave::load(&storage).unwrap().level.is_none());
+    let mut resources = load("intro", 0);
+    play(&mut resources, &frames(&[(40, &[VirtualKey::Right])]));
+    let mut slow = resources.stats.clone();
+    slow.frames += 100;
+    slow.kills = slow.max_kills;
+    let mut inv = Inventory::new();
+    inv.ammo[AmmoType::Shell] = 7;
+    let mut campaign = CampaignSave::new();
+    campaign.complete_level(&resources.stats, "chasm", inv.clone());
+    campaign.complete_level(&slow, "chasm", inv);
+    campaign.save(&mut storage).unwrap();
+    let loaded = CampaignSave::load(&storage).unwrap();
+    assert_eq!(loaded.level.as_deref(), Some("chasm"));
+    assert_eq!(loaded.inventory.ammo[AmmoType::Shell], 7);
+    let record = loaded.records["intro"];
+    assert_eq!(record.completions, 2);
+    assert_eq!(record.best_frames, 40);
+    assert_eq!(record.best_kills, 100);
+    assert!(CampaignSave::parse("record intro 1 2").is_err());
+    let mut inv = Inventory::new();
+    assert!(inv.parse_line("ammo", &["1", "2"]).is_err());
+    assert!(inv.parse_line("ammo", &["1", "2", "3", "4"]).is_err());
+    assert!(inv.parse_line("ammo", &["1", "2", "3"]).unwrap());
+}
+
+#[test]
+fn demo_playback_stays_in_sync() {
+    let frames = frames(BUSY_SCRIPT);
+    for path in ["intro", "chasm"] {
+        let demo = Demo::parse(&record(path, 99, &frames).to_text()).unwrap();
+        let mut resources = load(&demo.level, demo.seed);
+        let mut player = DemoPlayer::new(demo);
+        let mut input = Input::new();
+        let mut profiler = Profiler::disabled();
+        while !player.is_finished() {
+            player.play_input(&mut input);
+            step(&mut resources, &input, &mut profiler);
+            player.check_state(&resources).unwrap();
+        }
+        assert_eq!(resources.stats.frames, frames.len() as u32);
+    }
+}
+
+#[test]
+fn demo_checks_out_of_range() {
+    assert!(Demo::parse("level intro\ncheck 30 -4 8 3\n").is_ok());
+    assert!(Demo::parse("level intro\ncheck -30 4 8 3\n").is_err());
+    assert!(Demo::parse("level intro\ncheck 30 4 8 256\n").is_err());
+    assert!(Demo::parse("level intro\ncheck 30 4 9999999999 3\n").is_err());
+}
+
+// replay a full-clear run recorded in game with --record and check it ends at the exit
+fn finish_level(path: &str, exit_switch: &str) {
+    let demo_path = assets_dir().join("demos").join(format!("{}.demo", path));
+    let text = std::fs::read_to_string(&demo_path).expect("couldn't read demo");
+    let demo = Demo::parse(&text).unwrap();
+    let mut resources = load(&demo.level, demo.seed);
+    let mut player = DemoPlayer::new(demo);
+    let mut input = Input::new();
+    let mut profiler = Profiler::disabled();
+    while !player.is_finished() {
+        player.play_input(&mut input);
+        step(&mut resources, &input, &mut profiler);
+        player.check_state(&resources).unwrap();
+    }
+    assert!(resources.script_engine.win_flag());
+    let s = &resources.stats;
+    assert_eq!(
+        progress(&resources),
+        (s.max_kills, s.max_items, s.max_secrets)
+    );
+    let (x, y, hp) = player_state(&resources);
+    assert!(hp > 0);
+    let world = resources.world_ref.lock().unwrap();
+    let mut q = world.query::<(&IntRect, &Switch)>();
+    let (_, (switch_rect, _)) = q
+        .iter()
+        .find(|(_, (_, sw))| sw.name == exit_switch)
+        .unwrap();
+    let player_rect = world.get::<&IntRect>(resources.player_id).unwrap();
+    assert!(
+        player_rect.intersects(switch_rect),
+        "finished at {}, {}",
+        x,
+        y
+    );
+}
+
+#[test]
+fn intro_demo_reaches_exit() {
+    finish_level("intro", "final_switch");
+}
+
+#[test]
+fn chasm_demo_reaches_exit() {
+    finish_level("chasm", "exit_switch");
+}